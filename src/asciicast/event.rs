@@ -1,13 +1,13 @@
-//! Module for modeling and serializing an [asciicast v2 event](https://docs.asciinema.org/manual/asciicast/v2/#event-stream).
+//! Module for modeling and serializing an [asciicast v2 event](https://docs.asciinema.org/manual/asciicast/v2/#event-stream) or [asciicast v3 event](https://docs.asciinema.org/manual/asciicast/v3/#event-stream).
 
 use serde::Serialize;
 use std::io::Write;
 
-/// An event of an asciicast v2 file.
+/// An event of an asciicast v2 or v3 file.
 // Adapted from https://github.com/asciinema/asciinema/blob/f0f908872ca0364128b546bcc8af918d2fc47566/src/asciicast/v2.rs#L38-L45
 #[derive(Debug)]
 pub(super) struct Event<'a> {
-    /// Indicates when the event happened, represented as the number of milliseconds since the beginning of the recording session.
+    /// Indicates when the event happened, represented as the number of microseconds since the beginning of the recording session (v2), or since the previous event (v3).
     time: u128,
     /// Type of the event.
     code: EventCode,
@@ -66,6 +66,14 @@ impl<'a> Event<'a> {
             data: dim,
         }
     }
+    /// Create a new exit event, with the exit status as data.
+    pub const fn exit(time: u128, status: &'a str) -> Self {
+        Self {
+            time,
+            code: EventCode::Exit,
+            data: status,
+        }
+    }
     /// Write the event to the writer.
     pub fn write(&self, writer: impl Write) -> Result<(), serde_json::Error> {
         let mut serializer = serde_json::Serializer::with_formatter(writer, Formatter);
@@ -82,6 +90,7 @@ enum EventCode {
     Input,
     Marker,
     Resize,
+    Exit,
 }
 
 impl Serialize for EventCode {
@@ -89,13 +98,14 @@ impl Serialize for EventCode {
     where
         S: serde::Serializer,
     {
-        use EventCode::{Input, Marker, Output, Resize};
+        use EventCode::{Exit, Input, Marker, Output, Resize};
 
         match self {
             Output => serializer.serialize_str("o"),
             Input => serializer.serialize_str("i"),
             Marker => serializer.serialize_str("m"),
             Resize => serializer.serialize_str("r"),
+            Exit => serializer.serialize_str("x"),
         }
     }
 }
@@ -135,6 +145,7 @@ mod tests {
                 Event::resize(1_002_000, "80x24"),
                 r#"[1.002000,"r","80x24"]"#,
             ),
+            (Event::exit(1_500_000, "1"), r#"[1.500000,"x","1"]"#),
         ];
 
         for (event, expected) in &pairs {
@@ -1,16 +1,17 @@
-//! Module for modeling and serializing an [asciicast v2 header](https://docs.asciinema.org/manual/asciicast/v2/#header).
+//! Module for modeling, serializing and parsing an [asciicast v2 header](https://docs.asciinema.org/manual/asciicast/v2/#header) or [asciicast v3 header](https://docs.asciinema.org/manual/asciicast/v3/#header).
 
-use super::util::capture_env_vars;
+use super::{ErrorType, util::capture_env_vars};
 use serde::Serialize;
 use serde::ser::SerializeStruct;
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
-/// The header of an asciicast v2 file.
+/// The header of an asciicast v2 or v3 file. For v3, the terminal type (`term.type`) is kept as the `TERM` environment variable.
 // From: https://github.com/asciinema/asciinema/blob/f0f908872ca0364128b546bcc8af918d2fc47566/src/asciicast/v2.rs##L9-L20))
-#[derive(Debug)]
-pub(super) struct Header {
-    /// The version of the asciicast format. Must be set to 2.
-    version: u8,
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    /// The version of the asciicast format. Either 2 or 3.
+    pub version: u8,
     /// Initial terminal width, i.e number of columns.
     pub width: u16,
     /// Initial terminal height, i.e number of rows.
@@ -23,13 +24,79 @@ pub(super) struct Header {
     pub title: Option<String>,
     /// Map of captured environment variables.
     pub env: Option<HashMap<String, String>>,
+    /// Tags of the asciicast. Only written in v3.
+    pub tags: Option<Vec<String>>,
+    /// Color theme of the recorded terminal. Written as `theme` in v2, and as `term.theme` in v3.
+    pub theme: Option<TermTheme>,
     // Not implemented fields:
     // Duration of the whole recording in seconds (when it's known upfront).
     // duration: Option<u64>,
     // Command that was recorded.
     // command: Option<String>,
-    // Color theme of the recorded terminal.
-    // theme: Option<V2Theme>,
+}
+
+/// The [color theme](https://docs.asciinema.org/manual/asciicast/v3/#theme) of a terminal, with colors in `#rrggbb` format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermTheme {
+    /// Default foreground color.
+    pub fg: String,
+    /// Default background color.
+    pub bg: String,
+    /// Palette of 8 or 16 colors.
+    pub palette: Vec<String>,
+}
+
+impl Serialize for TermTheme {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("TermTheme", 3)?;
+        state.serialize_field("fg", &self.fg)?;
+        state.serialize_field("bg", &self.bg)?;
+        state.serialize_field("palette", &self.palette.join(":"))?;
+        state.end()
+    }
+}
+
+impl TermTheme {
+    /// Parse a theme from a JSON object, with `fg`, `bg` and a colon-separated `palette`, like in the header.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`Json`](ErrorType::Json) error if the string is not valid JSON, or a [`MalformedHeader`](ErrorType::MalformedHeader) error if a color is missing or malformed, or the palette does not have 8 or 16 colors.
+    pub fn parse(s: &str) -> Result<Self, ErrorType> {
+        let value: Value = serde_json::from_str(s)?;
+        Self::from_value(&value).ok_or(ErrorType::MalformedHeader)
+    }
+    /// Convert a JSON value into a theme, if it is well-formed.
+    fn from_value(value: &Value) -> Option<Self> {
+        let object = value.as_object()?;
+        let color = |key: &str| {
+            let color = object.get(key)?.as_str()?;
+            is_color(color).then(|| color.to_string())
+        };
+        let palette: Vec<String> = object
+            .get("palette")?
+            .as_str()?
+            .split(':')
+            .map(|color| is_color(color).then(|| color.to_string()))
+            .collect::<Option<_>>()?;
+        if !matches!(palette.len(), 8 | 16) {
+            return None;
+        }
+        Some(Self {
+            fg: color("fg")?,
+            bg: color("bg")?,
+            palette,
+        })
+    }
+}
+
+/// Check whether given string is a color in `#rrggbb` format.
+fn is_color(s: &str) -> bool {
+    s.strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
 }
 
 fn serialize_or_skip<S, T>(
@@ -54,6 +121,9 @@ impl Serialize for Header {
     where
         S: serde::Serializer,
     {
+        if self.version >= 3 {
+            return self.serialize_v3(serializer);
+        }
         // Count length of fields
         let mut len = 3;
         if self.timestamp.is_some() {
@@ -68,6 +138,9 @@ impl Serialize for Header {
         if self.env.is_some() {
             len += 1;
         }
+        if self.theme.is_some() {
+            len += 1;
+        }
 
         let mut state = serializer.serialize_struct("Header", len)?;
         state.serialize_field("version", &self.version)?;
@@ -79,13 +152,85 @@ impl Serialize for Header {
         serialize_or_skip(&mut state, "idle_time_limit", self.idle_time_limit.as_ref())?;
         serialize_or_skip(&mut state, "title", self.title.as_ref())?;
         serialize_or_skip(&mut state, "env", self.env.as_ref())?;
+        serialize_or_skip(&mut state, "theme", self.theme.as_ref())?;
+
+        state.end()
+    }
+}
+
+/// The [terminal information](https://docs.asciinema.org/manual/asciicast/v3/#term) of an asciicast v3 header.
+struct Term<'a> {
+    /// Terminal width, i.e number of columns.
+    cols: u16,
+    /// Terminal height, i.e number of rows.
+    rows: u16,
+    /// Terminal type, e.g. `xterm-256color`.
+    kind: Option<&'a String>,
+    /// Terminal color theme.
+    theme: Option<&'a TermTheme>,
+}
 
+impl Serialize for Term<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let len = 2 + usize::from(self.kind.is_some()) + usize::from(self.theme.is_some());
+        let mut state = serializer.serialize_struct("Term", len)?;
+        state.serialize_field("cols", &self.cols)?;
+        state.serialize_field("rows", &self.rows)?;
+        serialize_or_skip(&mut state, "type", self.kind)?;
+        serialize_or_skip(&mut state, "theme", self.theme)?;
         state.end()
     }
 }
 
 impl Header {
-    /// Create a new header with default width and height. (80x24)
+    /// Serialize as an asciicast v3 header, where the terminal size, type and theme are nested in `term`, and `TERM` is not part of `env`.
+    fn serialize_v3<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let term = Term {
+            cols: self.width,
+            rows: self.height,
+            kind: self.env.as_ref().and_then(|env| env.get("TERM")),
+            theme: self.theme.as_ref(),
+        };
+        let env: Option<HashMap<&String, &String>> = self.env.as_ref().and_then(|env| {
+            let env: HashMap<_, _> = env.iter().filter(|(name, _)| *name != "TERM").collect();
+            (!env.is_empty()).then_some(env)
+        });
+
+        let mut len = 2;
+        for present in [
+            self.timestamp.is_some(),
+            self.idle_time_limit.is_some(),
+            self.title.is_some(),
+            env.is_some(),
+            self.tags.is_some(),
+        ] {
+            len += usize::from(present);
+        }
+
+        let mut state = serializer.serialize_struct("Header", len)?;
+        state.serialize_field("version", &self.version)?;
+        state.serialize_field("term", &term)?;
+
+        // Skip `None` fields
+        serialize_or_skip(&mut state, "timestamp", self.timestamp.as_ref())?;
+        serialize_or_skip(&mut state, "idle_time_limit", self.idle_time_limit.as_ref())?;
+        serialize_or_skip(&mut state, "title", self.title.as_ref())?;
+        serialize_or_skip(&mut state, "env", env.as_ref())?;
+        serialize_or_skip(&mut state, "tags", self.tags.as_ref())?;
+
+        state.end()
+    }
+}
+
+impl Header {
+    /// Create a new v2 header with default width and height (80x24), capturing the `SHELL` and `TERM` environment variables.
+    #[must_use]
     pub fn new() -> Self {
         let (width, height) = (80, 24); // Default values for testing
         Self {
@@ -99,7 +244,101 @@ impl Header {
                 "SHELL".to_string(),
                 "TERM".to_string(),
             ])),
+            tags: None,
+            theme: None,
+        }
+    }
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Header {
+    /// Parse a header from a line of JSON, in either v2 or v3 format.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`Json`](ErrorType::Json) error if the line is not valid JSON, an [`UnsupportedVersion`](ErrorType::UnsupportedVersion) error if the version is not 2 or 3, or a [`MalformedHeader`](ErrorType::MalformedHeader) error if a field is missing or has an unexpected type.
+    pub fn parse(line: &str) -> Result<Self, ErrorType> {
+        let value: Value = serde_json::from_str(line)?;
+        let object = value.as_object().ok_or(ErrorType::MalformedHeader)?;
+        let version = match object.get("version").and_then(Value::as_u64) {
+            Some(2) => 2,
+            Some(3) => 3,
+            _ => return Err(ErrorType::UnsupportedVersion),
+        };
+        let (width, height, term_type, theme) = if version == 2 {
+            (
+                u16_field(object, "width")?,
+                u16_field(object, "height")?,
+                None,
+                optional(object, "theme", TermTheme::from_value)?,
+            )
+        } else {
+            let term = object
+                .get("term")
+                .and_then(Value::as_object)
+                .ok_or(ErrorType::MalformedHeader)?;
+            let term_type = optional(term, "type", |v| v.as_str().map(ToString::to_string))?;
+            (
+                u16_field(term, "cols")?,
+                u16_field(term, "rows")?,
+                term_type,
+                optional(term, "theme", TermTheme::from_value)?,
+            )
+        };
+
+        let mut env = optional(object, "env", |v| {
+            v.as_object()?
+                .iter()
+                .map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+                .collect::<Option<HashMap<_, _>>>()
+        })?;
+        if let Some(term_type) = term_type {
+            env.get_or_insert_with(HashMap::new)
+                .insert("TERM".to_string(), term_type);
         }
+
+        Ok(Self {
+            version,
+            width,
+            height,
+            timestamp: optional(object, "timestamp", Value::as_u64)?,
+            idle_time_limit: optional(object, "idle_time_limit", Value::as_f64)?,
+            title: optional(object, "title", |v| v.as_str().map(ToString::to_string))?,
+            env,
+            tags: optional(object, "tags", |v| {
+                v.as_array()?
+                    .iter()
+                    .map(|tag| tag.as_str().map(ToString::to_string))
+                    .collect()
+            })?,
+            theme,
+        })
+    }
+}
+
+/// Get a required field that fits in `u16`.
+fn u16_field(object: &Map<String, Value>, key: &str) -> Result<u16, ErrorType> {
+    object
+        .get(key)
+        .and_then(Value::as_u64)
+        .and_then(|v| u16::try_from(v).ok())
+        .ok_or(ErrorType::MalformedHeader)
+}
+
+/// Get an optional field, treating `null` as absent. Returns a [`MalformedHeader`](ErrorType::MalformedHeader) error if the field is present but cannot be converted.
+fn optional<T>(
+    object: &Map<String, Value>,
+    key: &str,
+    convert: impl Fn(&Value) -> Option<T>,
+) -> Result<Option<T>, ErrorType> {
+    match object.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => convert(value).map(Some).ok_or(ErrorType::MalformedHeader),
     }
 }
 
@@ -117,10 +356,110 @@ mod tests {
             idle_time_limit: Some(2.0),
             title: Some("My asciicast".to_string()),
             env: None,
+            tags: Some(vec!["demo".to_string()]),
+            theme: None,
         };
         let expected =
             r#"{"version":2,"width":80,"height":24,"idle_time_limit":2.0,"title":"My asciicast"}"#;
         assert_eq!(serde_json::to_string(&header)?, expected);
         Ok(())
     }
+
+    #[test]
+    fn header_serialize_v3() -> serde_json::Result<()> {
+        let mut header = Header {
+            version: 3,
+            width: 80,
+            height: 24,
+            timestamp: Some(1_000_000),
+            idle_time_limit: None,
+            title: Some("My asciicast".to_string()),
+            env: Some(HashMap::from([(
+                "TERM".to_string(),
+                "xterm-256color".to_string(),
+            )])),
+            tags: Some(vec!["demo".to_string()]),
+            theme: None,
+        };
+        let expected = r#"{"version":3,"term":{"cols":80,"rows":24,"type":"xterm-256color"},"timestamp":1000000,"title":"My asciicast","tags":["demo"]}"#;
+        assert_eq!(serde_json::to_string(&header)?, expected);
+
+        header.env = Some(HashMap::from([(
+            "SHELL".to_string(),
+            "/bin/bash".to_string(),
+        )]));
+        header.tags = None;
+        let expected = r#"{"version":3,"term":{"cols":80,"rows":24},"timestamp":1000000,"title":"My asciicast","env":{"SHELL":"/bin/bash"}}"#;
+        assert_eq!(serde_json::to_string(&header)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn header_parse() {
+        let header = Header::parse(
+            r#"{"version":2,"width":80,"height":24,"idle_time_limit":2.0,"title":"My asciicast","env":{"SHELL":"/bin/bash"}}"#,
+        )
+        .unwrap();
+        assert_eq!(header.version, 2);
+        assert_eq!((header.width, header.height), (80, 24));
+        assert_eq!(header.idle_time_limit, Some(2.0));
+        assert_eq!(header.title.as_deref(), Some("My asciicast"));
+        assert_eq!(header.env.unwrap()["SHELL"], "/bin/bash");
+
+        let header = Header::parse(
+            r#"{"version":3,"term":{"cols":100,"rows":30,"type":"xterm-256color"},"timestamp":1000000,"tags":["demo"]}"#,
+        )
+        .unwrap();
+        assert_eq!(header.version, 3);
+        assert_eq!((header.width, header.height), (100, 30));
+        assert_eq!(header.timestamp, Some(1_000_000));
+        assert_eq!(header.env.unwrap()["TERM"], "xterm-256color");
+        assert_eq!(header.tags, Some(vec!["demo".to_string()]));
+    }
+
+    #[test]
+    fn header_round_trip() {
+        for line in [
+            r#"{"version":2,"width":80,"height":24,"timestamp":1000000,"title":"Test"}"#,
+            r#"{"version":3,"term":{"cols":80,"rows":24,"type":"xterm"},"idle_time_limit":1.5,"tags":["a","b"]}"#,
+            r##"{"version":2,"width":80,"height":24,"theme":{"fg":"#d0d0d0","bg":"#212121","palette":"#000000:#cc0000:#4e9a06:#c4a000:#3465a4:#75507b:#06989a:#d3d7cf"}}"##,
+            r##"{"version":3,"term":{"cols":80,"rows":24,"theme":{"fg":"#d0d0d0","bg":"#212121","palette":"#000000:#cc0000:#4e9a06:#c4a000:#3465a4:#75507b:#06989a:#d3d7cf"}}}"##,
+        ] {
+            let header = Header::parse(line).unwrap();
+            assert_eq!(serde_json::to_string(&header).unwrap(), line);
+        }
+    }
+
+    #[test]
+    fn header_parse_errors() {
+        let cases = [
+            ("[]", ErrorType::MalformedHeader),
+            (
+                r#"{"version":1,"width":80,"height":24}"#,
+                ErrorType::UnsupportedVersion,
+            ),
+            (r#"{"width":80,"height":24}"#, ErrorType::UnsupportedVersion),
+            (r#"{"version":2,"width":80}"#, ErrorType::MalformedHeader),
+            (
+                r#"{"version":2,"width":-1,"height":24}"#,
+                ErrorType::MalformedHeader,
+            ),
+            (
+                r#"{"version":3,"width":80,"height":24}"#,
+                ErrorType::MalformedHeader,
+            ),
+            (
+                r#"{"version":2,"width":80,"height":24,"title":1}"#,
+                ErrorType::MalformedHeader,
+            ),
+            (
+                r##"{"version":3,"term":{"cols":80,"rows":24,"theme":{"fg":"#fff","bg":"#000000","palette":"#000000"}}}"##,
+                ErrorType::MalformedHeader,
+            ),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(Header::parse(line).unwrap_err(), expected, "Line: {line}");
+        }
+        assert!(matches!(Header::parse("{"), Err(ErrorType::Json(_))));
+    }
 }
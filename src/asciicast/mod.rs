@@ -1,14 +1,28 @@
-//! Module for modeling and streaming [asciicast v2](https://docs.asciinema.org/manual/asciicast/v2/) content.
+//! Module for modeling and streaming [asciicast v2](https://docs.asciinema.org/manual/asciicast/v2/) or [asciicast v3](https://docs.asciinema.org/manual/asciicast/v3/) content.
 
 mod event;
 mod header;
-use super::{ErrorType, util};
+mod process;
+mod reader;
+use super::{Error, ErrorType, util};
 use event::Event;
-use header::Header;
+pub use header::{Header, TermTheme};
+pub use process::{cap_idle, concat, cut, speed};
+pub use reader::{EventKind, Reader, TimedEvent};
 use serde_json::ser::to_writer;
 use std::{collections::HashMap, io::Write};
 
-/// An [asciicast v2](https://docs.asciinema.org/manual/asciicast/v2/) instance, streaming content to a writer.
+/// Version of the asciicast format to write.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// [asciicast v2](https://docs.asciinema.org/manual/asciicast/v2/), with absolute timestamps.
+    #[default]
+    V2,
+    /// [asciicast v3](https://docs.asciinema.org/manual/asciicast/v3/), with intervals relative to the previous event, as used by asciinema 3.
+    V3,
+}
+
+/// An [asciicast v2](https://docs.asciinema.org/manual/asciicast/v2/) or [asciicast v3](https://docs.asciinema.org/manual/asciicast/v3/) instance, streaming content to a writer.
 ///
 /// ## Instantiation
 ///
@@ -24,6 +38,10 @@ use std::{collections::HashMap, io::Write};
 /// - [`idle_time_limit`](AsciiCast::idle_time_limit): Set the idle time limit.
 /// - [`title`](AsciiCast::title): Set the title of the asciicast.
 /// - [`capture`](AsciiCast::capture): Set the captured environment variables.
+/// - [`format`](AsciiCast::format): Set the version of the asciicast format, defaulting to v2.
+/// - [`tags`](AsciiCast::tags): Set the tags of the asciicast (v3 only).
+/// - [`theme`](AsciiCast::theme): Set the color theme of the recorded terminal.
+/// - [`header`](AsciiCast::header): Replace the whole header, e.g. one read by a [`Reader`], also setting the format by its version.
 ///
 /// After you've finished, you can write the header using the [`write_header`](AsciiCast::write_header) method explicitly. If you don't, the header will be written implicitly when you write the first event, or when the asciicast instance is dropped. Note that the header can only be written once, either explicitly or implicitly, or a [`HeaderAlreadyWritten`](ErrorType::HeaderAlreadyWritten) error will be returned.
 ///
@@ -35,6 +53,10 @@ use std::{collections::HashMap, io::Write};
 /// - [`input`](AsciiCast::input): Write an input event.
 /// - [`marker`](AsciiCast::marker): Write a marker event.
 /// - [`resize`](AsciiCast::resize): Write a resize event.
+/// - [`exit`](AsciiCast::exit): Write an exit event (v3 only).
+/// - [`write_event`](AsciiCast::write_event): Write an event read by a [`Reader`].
+///
+/// Events are given absolute times in microseconds, which are converted to intervals between events for v3.
 ///
 /// ## Output
 ///
@@ -110,6 +132,10 @@ where
 {
     header: Option<Header>,
     writer: &'a mut T,
+    /// Format of the asciicast.
+    format: Format,
+    /// Time of the previous event in microseconds, for relative intervals in v3.
+    last: u128,
 }
 
 impl<'a, T> AsciiCast<'a, T>
@@ -139,6 +165,8 @@ where
         Self {
             header: Some(Header::new()),
             writer,
+            format: Format::V2,
+            last: 0,
         }
     }
 
@@ -201,6 +229,52 @@ where
         };
         Ok(self)
     }
+    /// Set the version of the asciicast format.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`HeaderAlreadyWritten`](ErrorType::HeaderAlreadyWritten) error if the header has already been written.
+    pub fn format(&mut self, format: Format) -> Result<&mut Self, ErrorType> {
+        self.get_header_mut()?.version = match format {
+            Format::V2 => 2,
+            Format::V3 => 3,
+        };
+        self.format = format;
+        Ok(self)
+    }
+    /// Set the [tags of the asciicast](https://docs.asciinema.org/manual/asciicast/v3/#tags). Ignored for v2, which does not support tags.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`HeaderAlreadyWritten`](ErrorType::HeaderAlreadyWritten) error if the header has already been written.
+    pub fn tags(&mut self, tags: Vec<String>) -> Result<&mut Self, ErrorType> {
+        self.get_header_mut()?.tags = if tags.is_empty() { None } else { Some(tags) };
+        Ok(self)
+    }
+    /// Set the [color theme of the recorded terminal](https://docs.asciinema.org/manual/asciicast/v3/#theme).
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`HeaderAlreadyWritten`](ErrorType::HeaderAlreadyWritten) error if the header has already been written.
+    pub fn theme(&mut self, theme: TermTheme) -> Result<&mut Self, ErrorType> {
+        self.get_header_mut()?.theme = Some(theme);
+        Ok(self)
+    }
+    /// Replace the whole header, setting the format by its version (v3 if the version is at least 3, v2 otherwise).
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`HeaderAlreadyWritten`](ErrorType::HeaderAlreadyWritten) error if the header has already been written.
+    pub fn header(&mut self, header: Header) -> Result<&mut Self, ErrorType> {
+        let format = if header.version >= 3 {
+            Format::V3
+        } else {
+            Format::V2
+        };
+        *self.get_header_mut()? = header;
+        self.format = format;
+        Ok(self)
+    }
     /// Write the header to the writer.
     ///
     /// ## Errors
@@ -240,6 +314,7 @@ where
     /// Returns a [`Json`](ErrorType::Json) error if serialization fails, or an [`Io`](ErrorType::Io) error if writing to the writer fails.
     pub fn output(&mut self, time: u128, data: &str) -> Result<&mut Self, ErrorType> {
         self.try_write_header()?;
+        let time = self.time(time);
         self.event(&Event::output(time, data))?;
         Ok(self)
     }
@@ -250,6 +325,7 @@ where
     /// Returns a [`Json`](ErrorType::Json) error if serialization fails, or an [`Io`](ErrorType::Io) error if writing to the writer fails.
     pub fn input(&mut self, time: u128, data: &str) -> Result<&mut Self, ErrorType> {
         self.try_write_header()?;
+        let time = self.time(time);
         self.event(&Event::input(time, data))?;
         Ok(self)
     }
@@ -260,6 +336,7 @@ where
     /// Returns a [`Json`](ErrorType::Json) error if serialization fails, or an [`Io`](ErrorType::Io) error if writing to the writer fails.
     pub fn marker(&mut self, time: u128, name: &str) -> Result<&mut Self, ErrorType> {
         self.try_write_header()?;
+        let time = self.time(time);
         self.event(&Event::marker(time, name))?;
         Ok(self)
     }
@@ -270,9 +347,50 @@ where
     /// Returns a [`Json`](ErrorType::Json) error if serialization fails, or an [`Io`](ErrorType::Io) error if writing to the writer fails.
     pub fn resize(&mut self, time: u128, columns: u16, rows: u16) -> Result<&mut Self, ErrorType> {
         self.try_write_header()?;
+        let time = self.time(time);
         self.event(&Event::resize(time, &format!("{columns}x{rows}")))?;
         Ok(self)
     }
+    /// Write an [exit event](https://docs.asciinema.org/manual/asciicast/v3/#x-exit) with given exit status. Does nothing for v2, which does not support exit events.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`Json`](ErrorType::Json) error if serialization fails, or an [`Io`](ErrorType::Io) error if writing to the writer fails.
+    pub fn exit(&mut self, time: u128, status: i32) -> Result<&mut Self, ErrorType> {
+        if self.format == Format::V2 {
+            return Ok(self);
+        }
+        self.try_write_header()?;
+        let time = self.time(time);
+        self.event(&Event::exit(time, &status.to_string()))?;
+        Ok(self)
+    }
+    /// Write an event read by a [`Reader`], dispatching to the method of its kind.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`Json`](ErrorType::Json) error if serialization fails, or an [`Io`](ErrorType::Io) error if writing to the writer fails.
+    pub fn write_event(&mut self, event: &TimedEvent) -> Result<&mut Self, ErrorType> {
+        let time = event.time;
+        match &event.kind {
+            EventKind::Output(data) => self.output(time, data),
+            EventKind::Input(data) => self.input(time, data),
+            EventKind::Marker(name) => self.marker(time, name),
+            EventKind::Resize(columns, rows) => self.resize(time, *columns, *rows),
+            EventKind::Exit(status) => self.exit(time, *status),
+        }
+    }
+    /// Convert an absolute time into the time to be written, i.e. the interval since the previous event for v3.
+    const fn time(&mut self, time: u128) -> u128 {
+        match self.format {
+            Format::V2 => time,
+            Format::V3 => {
+                let interval = time.saturating_sub(self.last);
+                self.last = time;
+                interval
+            }
+        }
+    }
     /// Write an event to the writer.
     ///
     /// ## Errors
@@ -377,6 +495,73 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn v3() -> Result<(), ErrorType> {
+        let mut writer = Vec::new();
+
+        AsciiCast::new(&mut writer)
+            .format(Format::V3)?
+            .width(80)?
+            .height(24)?
+            .title("Test".to_string())?
+            .capture(HashMap::new())?
+            .tags(vec!["demo".to_string()])?
+            .output(100_000, "Hello, world!")?
+            .input(300_000, "\r")?
+            .marker(300_000, "marker")?
+            .resize(1_300_000, 80, 25)?
+            .exit(1_500_000, 1)?;
+
+        let expected = r#"{"version":3,"term":{"cols":80,"rows":24},"title":"Test","tags":["demo"]}
+[0.100000,"o","Hello, world!"]
+[0.200000,"i","\r"]
+[0.000000,"m","marker"]
+[1.000000,"r","80x25"]
+[0.200000,"x","1"]
+"#;
+        assert_eq!(String::from_utf8(writer).unwrap(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn v2_ignores_v3_only() -> Result<(), ErrorType> {
+        let mut writer = Vec::new();
+
+        AsciiCast::new(&mut writer)
+            .capture(HashMap::new())?
+            .tags(vec!["demo".to_string()])?
+            .output(100_000, "Hello")?
+            .exit(200_000, 0)?;
+
+        let expected = r#"{"version":2,"width":80,"height":24}
+[0.100000,"o","Hello"]
+"#;
+        assert_eq!(String::from_utf8(writer).unwrap(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn copy_events() -> Result<(), Error> {
+        let cast = r#"{"version":3,"term":{"cols":80,"rows":24},"title":"Test"}
+[0.100000,"o","Hello"]
+[0.200000,"r","100x30"]
+[0.000000,"x","0"]
+"#;
+        let reader = Reader::new(cast.as_bytes())?;
+        let mut writer = Vec::new();
+        let mut asciicast = AsciiCast::new(&mut writer);
+        asciicast
+            .header(reader.header().clone())
+            .map_err(|e| e.with_line(0))?;
+        for event in reader {
+            asciicast.write_event(&event?).map_err(|e| e.with_line(0))?;
+        }
+        drop(asciicast);
+
+        assert_eq!(String::from_utf8(writer).unwrap(), cast);
+        Ok(())
+    }
+
     #[test]
     fn explicit_header_already_written() -> Result<(), ErrorType> {
         let mut writer = std::io::sink();
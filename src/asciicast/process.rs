@@ -0,0 +1,432 @@
+//! Module for post-processing asciicast events, as streaming iterator adapters.
+
+use super::{Error, ErrorType, EventKind, Header, Reader, TimedEvent};
+use std::{collections::VecDeque, io::BufRead};
+
+/// Scale the speed of given events by a factor, e.g. `1.5` to play 1.5 times as fast. The idle time limit in the header is scaled accordingly.
+///
+/// ## Errors
+///
+/// Returns an [`InvalidSpeed`](ErrorType::InvalidSpeed) error if the factor is not a positive finite number.
+pub fn speed<I>(
+    header: &mut Header,
+    events: I,
+    factor: f64,
+) -> Result<impl Iterator<Item = Result<TimedEvent, Error>> + use<I>, Error>
+where
+    I: Iterator<Item = Result<TimedEvent, Error>>,
+{
+    if factor <= 0.0 || !factor.is_finite() {
+        return Err(ErrorType::InvalidSpeed.with_line(0));
+    }
+    if let Some(limit) = &mut header.idle_time_limit {
+        *limit /= factor;
+    }
+    Ok(events.map(move |event| {
+        event.map(|mut event| {
+            #[allow(
+                clippy::cast_precision_loss,
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss
+            )]
+            let time = (event.time as f64 / factor).round() as u128;
+            event.time = time;
+            event
+        })
+    }))
+}
+
+/// Cap the gaps between given events at a limit in microseconds (µs), shifting later events back accordingly.
+pub fn cap_idle<I>(events: I, limit: u128) -> impl Iterator<Item = Result<TimedEvent, Error>>
+where
+    I: Iterator<Item = Result<TimedEvent, Error>>,
+{
+    // Time of the previous event, before and after capping
+    let mut last = (0, 0);
+    events.map(move |event| {
+        event.map(|mut event| {
+            let gap = event.time.saturating_sub(last.0).min(limit);
+            last = (event.time, last.1 + gap);
+            event.time = last.1;
+            event
+        })
+    })
+}
+
+/// Remove the events between the markers labelled `from` and `to`, or till the end if `to` is `None`.
+///
+/// Later events are shifted back by the length of the removed range, and the markers themselves are kept. The last resize event in the range is kept as well, moved to the ending marker, so that the terminal size after the cut is right. An error is yielded at the end, if a required marker is not found.
+pub fn cut<I>(
+    events: I,
+    from: String,
+    to: Option<String>,
+) -> impl Iterator<Item = Result<TimedEvent, Error>>
+where
+    I: Iterator<Item = Result<TimedEvent, Error>>,
+{
+    let mut cut = Cut {
+        from,
+        to,
+        state: CutState::Before,
+        resize: None,
+    };
+    events
+        .map(Some)
+        .chain(std::iter::once(None))
+        .flat_map(move |event| match event {
+            Some(Ok(event)) => cut.process(event).map(|event| event.map(Ok)),
+            Some(Err(e)) => [Some(Err(e)), None],
+            None => [cut.finish().err().map(Err), None],
+        })
+        .flatten()
+}
+
+/// State of cutting a range of events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CutState {
+    /// Before the starting marker.
+    Before,
+    /// Inside the range, started at given time.
+    Inside(u128),
+    /// After the ending marker, with given time removed.
+    After(u128),
+}
+
+/// Cutting a range of events between two markers.
+struct Cut {
+    /// Label of the starting marker.
+    from: String,
+    /// Label of the ending marker, or `None` for the end of the cast.
+    to: Option<String>,
+    /// Current state.
+    state: CutState,
+    /// The last resize event inside the range.
+    resize: Option<TimedEvent>,
+}
+
+impl Cut {
+    /// Process an event, returning it (with adjusted time) if it is kept, preceded by the last resize event inside the range at the ending marker.
+    fn process(&mut self, mut event: TimedEvent) -> [Option<TimedEvent>; 2] {
+        let label = match &event.kind {
+            EventKind::Marker(label) => Some(label.as_str()),
+            _ => None,
+        };
+        match self.state {
+            CutState::Before => {
+                if label == Some(self.from.as_str()) {
+                    self.state = CutState::Inside(event.time);
+                }
+                [Some(event), None]
+            }
+            CutState::Inside(start) => {
+                if label.is_some() && label == self.to.as_deref() {
+                    let removed = event.time.saturating_sub(start);
+                    self.state = CutState::After(removed);
+                    event.time = start;
+                    let resize = self.resize.take().map(|mut resize| {
+                        resize.time = start;
+                        resize
+                    });
+                    [resize, Some(event)]
+                } else {
+                    if matches!(event.kind, EventKind::Resize(..)) {
+                        self.resize = Some(event);
+                    }
+                    [None, None]
+                }
+            }
+            CutState::After(removed) => {
+                event.time = event.time.saturating_sub(removed);
+                [Some(event), None]
+            }
+        }
+    }
+    /// Check that the required markers have been found.
+    fn finish(&self) -> Result<(), Error> {
+        match self.state {
+            CutState::Before => Err(ErrorType::MarkerNotFound(self.from.clone()).with_line(0)),
+            CutState::Inside(_) => self.to.as_ref().map_or(Ok(()), |to| {
+                Err(ErrorType::MarkerNotFound(to.clone()).with_line(0))
+            }),
+            CutState::After(_) => Ok(()),
+        }
+    }
+}
+
+/// Concatenate given casts sequentially, with a gap in microseconds (µs) between them. Returns the header of the first cast, and the combined events.
+///
+/// Each cast starts after the last event of the previous one (plus the gap), with a marker named after its title (or `Chapter N` if it has none). A resize event is inserted where the terminal size differs from the previous cast, and exit events are dropped, except those of the last cast. Errors are yielded as they are, so give each reader a [file](Reader::file) to tell which cast they come from.
+///
+/// ## Panics
+///
+/// Panics if no cast is given.
+pub fn concat<R: BufRead>(
+    readers: Vec<Reader<R>>,
+    gap: u128,
+) -> (Header, impl Iterator<Item = Result<TimedEvent, Error>>) {
+    let header = readers
+        .first()
+        .expect("At least one cast should be given")
+        .header()
+        .clone();
+    let concat = Concat {
+        size: (header.width, header.height),
+        readers: readers.into_iter(),
+        current: None,
+        pending: VecDeque::new(),
+        chapter: 0,
+        offset: 0,
+        end: 0,
+        gap,
+    };
+    (header, concat)
+}
+
+/// Iterator over the events of concatenated casts.
+struct Concat<R> {
+    /// Casts yet to be started.
+    readers: std::vec::IntoIter<Reader<R>>,
+    /// The cast being read.
+    current: Option<Reader<R>>,
+    /// Inserted events to be yielded before those of the current cast.
+    pending: VecDeque<TimedEvent>,
+    /// Number of casts started.
+    chapter: usize,
+    /// Start time of the current cast.
+    offset: u128,
+    /// Time of the last event so far.
+    end: u128,
+    /// Current terminal size.
+    size: (u16, u16),
+    /// Gap between casts.
+    gap: u128,
+}
+
+impl<R: BufRead> Concat<R> {
+    /// Start the next cast, inserting a resize event if needed and a chapter marker.
+    fn start(&mut self, reader: Reader<R>) {
+        let header = reader.header();
+        if self.chapter > 0 {
+            self.offset = self.end + self.gap;
+            self.end = self.offset;
+        }
+        self.chapter += 1;
+
+        let size = (header.width, header.height);
+        if size != self.size {
+            self.size = size;
+            self.pending.push_back(TimedEvent {
+                time: self.offset,
+                kind: EventKind::Resize(size.0, size.1),
+            });
+        }
+        let title = header
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Chapter {}", self.chapter));
+        self.pending.push_back(TimedEvent {
+            time: self.offset,
+            kind: EventKind::Marker(title),
+        });
+        self.current = Some(reader);
+    }
+}
+
+impl<R: BufRead> Iterator for Concat<R> {
+    type Item = Result<TimedEvent, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if let Some(reader) = &mut self.current {
+                match reader.next() {
+                    Some(Ok(mut event)) => {
+                        event.time += self.offset;
+                        self.end = self.end.max(event.time);
+                        match event.kind {
+                            EventKind::Resize(columns, rows) => self.size = (columns, rows),
+                            // Only the last cast ends the recording
+                            EventKind::Exit(_) if self.readers.len() > 0 => continue,
+                            _ => {}
+                        }
+                        return Some(Ok(event));
+                    }
+                    Some(Err(e)) => return Some(Err(e)),
+                    None => self.current = None,
+                }
+            }
+            let reader = self.readers.next()?;
+            self.start(reader);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create output events at given times, with markers labelled by their data.
+    fn events(spec: &[(u128, &str)]) -> Vec<Result<TimedEvent, Error>> {
+        spec.iter()
+            .map(|(time, data)| {
+                let kind = data.strip_prefix('!').map_or_else(
+                    || EventKind::Output((*data).to_string()),
+                    |label| EventKind::Marker(label.to_string()),
+                );
+                Ok(TimedEvent { time: *time, kind })
+            })
+            .collect()
+    }
+
+    /// Collect the times of given events.
+    fn times(events: impl Iterator<Item = Result<TimedEvent, Error>>) -> Vec<u128> {
+        events.map(|event| event.unwrap().time).collect()
+    }
+
+    #[test]
+    fn speed_up() {
+        let input = events(&[(0, "a"), (1_500_000, "b"), (3_000_001, "c")]);
+        let mut header = Header {
+            idle_time_limit: Some(3.0),
+            ..Header::default()
+        };
+        let output = times(speed(&mut header, input.into_iter(), 1.5).unwrap());
+        assert_eq!(output, [0, 1_000_000, 2_000_001]);
+        assert_eq!(header.idle_time_limit, Some(2.0));
+
+        for factor in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let err = speed(&mut header, std::iter::empty(), factor)
+                .err()
+                .unwrap();
+            assert_eq!(
+                err,
+                ErrorType::InvalidSpeed.with_line(0),
+                "Factor: {factor}"
+            );
+        }
+    }
+
+    #[test]
+    fn idle_capped() {
+        let input = events(&[
+            (500_000, "a"),
+            (600_000, "b"),
+            (5_600_000, "c"),
+            (5_700_000, "d"),
+        ]);
+        let output = times(cap_idle(input.into_iter(), 1_000_000));
+        assert_eq!(output, [500_000, 600_000, 1_600_000, 1_700_000]);
+    }
+
+    #[test]
+    fn cut_between_markers() {
+        let input = events(&[
+            (0, "a"),
+            (1_000_000, "!start"),
+            (2_000_000, "boring"),
+            (4_000_000, "!end"),
+            (5_000_000, "b"),
+        ]);
+        let output: Vec<TimedEvent> = cut(
+            input.into_iter(),
+            "start".to_string(),
+            Some("end".to_string()),
+        )
+        .collect::<Result<_, _>>()
+        .unwrap();
+        let expected = events(&[
+            (0, "a"),
+            (1_000_000, "!start"),
+            (1_000_000, "!end"),
+            (2_000_000, "b"),
+        ]);
+        let expected: Vec<TimedEvent> = expected.into_iter().collect::<Result<_, _>>().unwrap();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn cut_keeps_last_resize() {
+        let input = vec![
+            (0, EventKind::Marker("start".to_string())),
+            (1_000_000, EventKind::Resize(100, 30)),
+            (2_000_000, EventKind::Resize(120, 40)),
+            (3_000_000, EventKind::Output("hidden".to_string())),
+            (4_000_000, EventKind::Marker("end".to_string())),
+            (5_000_000, EventKind::Output("shown".to_string())),
+        ];
+        let input = input
+            .into_iter()
+            .map(|(time, kind)| Ok(TimedEvent { time, kind }));
+        let output: Vec<TimedEvent> = cut(input, "start".to_string(), Some("end".to_string()))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected = [
+            (0, EventKind::Marker("start".to_string())),
+            (0, EventKind::Resize(120, 40)),
+            (0, EventKind::Marker("end".to_string())),
+            (1_000_000, EventKind::Output("shown".to_string())),
+        ]
+        .map(|(time, kind)| TimedEvent { time, kind });
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn cut_tail() {
+        let input = events(&[(0, "a"), (1_000_000, "!tail"), (2_000_000, "b")]);
+        let output = times(cut(input.into_iter(), "tail".to_string(), None));
+        assert_eq!(output, [0, 1_000_000]);
+    }
+
+    #[test]
+    fn concat_casts() {
+        let casts = [
+            "{\"version\":2,\"width\":80,\"height\":24,\"title\":\"Intro\"}\n[1.0,\"o\",\"a\"]",
+            "{\"version\":3,\"term\":{\"cols\":100,\"rows\":30}}\n[0.5,\"o\",\"b\"]\n[0.5,\"r\",\"80x24\"]\n[0.0,\"x\",\"1\"]",
+            "{\"version\":3,\"term\":{\"cols\":80,\"rows\":24},\"title\":\"Outro\"}\n[0.0,\"o\",\"c\"]\n[0.5,\"x\",\"0\"]",
+        ];
+        let readers = casts
+            .iter()
+            .map(|cast| Reader::new(cast.as_bytes()).unwrap())
+            .collect();
+        let (header, events) = concat(readers, 2_000_000);
+        assert_eq!(header.title.as_deref(), Some("Intro"));
+
+        let events: Vec<TimedEvent> = events.collect::<Result<_, _>>().unwrap();
+        let expected = [
+            (0, EventKind::Marker("Intro".to_string())),
+            (1_000_000, EventKind::Output("a".to_string())),
+            (3_000_000, EventKind::Resize(100, 30)),
+            (3_000_000, EventKind::Marker("Chapter 2".to_string())),
+            (3_500_000, EventKind::Output("b".to_string())),
+            (4_000_000, EventKind::Resize(80, 24)),
+            (6_000_000, EventKind::Marker("Outro".to_string())),
+            (6_000_000, EventKind::Output("c".to_string())),
+            (6_500_000, EventKind::Exit(0)),
+        ]
+        .map(|(time, kind)| TimedEvent { time, kind });
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn cut_missing_marker() {
+        let input = events(&[(0, "a"), (1_000_000, "!start")]);
+        let mut output = cut(
+            input.into_iter(),
+            "start".to_string(),
+            Some("end".to_string()),
+        );
+        assert!(output.next().unwrap().is_ok());
+        assert!(output.next().unwrap().is_ok());
+        assert_eq!(
+            output.next().unwrap().unwrap_err(),
+            ErrorType::MarkerNotFound("end".to_string()).with_line(0)
+        );
+        assert!(output.next().is_none());
+
+        let input = events(&[(0, "a")]);
+        let errors = cut(input.into_iter(), "start".to_string(), None).filter(Result::is_err);
+        assert_eq!(errors.count(), 1);
+    }
+}
@@ -0,0 +1,268 @@
+//! Module for parsing [asciicast v2](https://docs.asciinema.org/manual/asciicast/v2/) or [asciicast v3](https://docs.asciinema.org/manual/asciicast/v3/) content.
+
+use super::{Error, ErrorType, Header};
+use serde_json::Value;
+use std::{
+    io::{BufRead, Lines},
+    path::PathBuf,
+};
+
+/// An event read from an asciicast, with its absolute time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedEvent {
+    /// When the event happened, in microseconds (µs) since the beginning of the recording session. For v3, intervals between events are accumulated into absolute times.
+    pub time: u128,
+    /// The kind of the event, with its data.
+    pub kind: EventKind,
+}
+
+/// Kind of an event, with its data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventKind {
+    /// Data written to the terminal (`o`).
+    Output(String),
+    /// Data read from the terminal (`i`).
+    Input(String),
+    /// A marker, with its label (`m`).
+    Marker(String),
+    /// Terminal resize, with the new number of columns and rows (`r`).
+    Resize(u16, u16),
+    /// Exit of the recorded process, with its exit status (`x`, v3 only).
+    Exit(i32),
+}
+
+impl TimedEvent {
+    /// Parse an event from a line of JSON, given the time of the previous event (for v3, where times are relative).
+    ///
+    /// ## Errors
+    ///
+    /// Returns a [`Json`](ErrorType::Json) error if the line is not valid JSON, or a [`MalformedEvent`](ErrorType::MalformedEvent) error if it is not a valid event.
+    fn parse(line: &str, version: u8, last: u128) -> Result<Self, ErrorType> {
+        let value: Value = serde_json::from_str(line)?;
+        let [time, code, data] = value.as_array().map(Vec::as_slice).unwrap_or_default() else {
+            return Err(ErrorType::MalformedEvent);
+        };
+        let time = time
+            .as_f64()
+            .filter(|time| time.is_finite() && *time >= 0.0)
+            .ok_or(ErrorType::MalformedEvent)?;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let time = (time * 1_000_000.0).round() as u128;
+        let time = if version >= 3 { last + time } else { time };
+
+        let data = data.as_str().ok_or(ErrorType::MalformedEvent)?.to_string();
+        let kind = match code.as_str() {
+            Some("o") => EventKind::Output(data),
+            Some("i") => EventKind::Input(data),
+            Some("m") => EventKind::Marker(data),
+            Some("r") => {
+                let (columns, rows) = data.split_once('x').ok_or(ErrorType::MalformedEvent)?;
+                let columns = columns.parse().map_err(|_| ErrorType::MalformedEvent)?;
+                let rows = rows.parse().map_err(|_| ErrorType::MalformedEvent)?;
+                EventKind::Resize(columns, rows)
+            }
+            Some("x") if version >= 3 => {
+                EventKind::Exit(data.parse().map_err(|_| ErrorType::MalformedEvent)?)
+            }
+            _ => return Err(ErrorType::MalformedEvent),
+        };
+        Ok(Self { time, kind })
+    }
+}
+
+/// A reader of asciicast v2 or v3 content, parsing the header on creation and yielding events as an iterator.
+///
+/// Errors are reported with the line number where they occurred. Empty lines are skipped, and so are comments (lines starting with `#`) in v3.
+///
+/// ## Example
+///
+/// ```rust
+/// use castwright::{EventKind, Reader};
+///
+/// let cast = r#"{"version":2,"width":80,"height":24}
+/// [0.500000,"o","Hello"]
+/// [1.000000,"m","end"]
+/// "#;
+/// let mut reader = Reader::new(cast.as_bytes()).unwrap();
+/// assert_eq!(reader.header().width, 80);
+/// let event = reader.next().unwrap().unwrap();
+/// assert_eq!(event.time, 500_000);
+/// assert_eq!(event.kind, EventKind::Output("Hello".to_string()));
+/// assert_eq!(reader.count(), 1);
+/// ```
+#[derive(Debug)]
+pub struct Reader<R> {
+    /// Lines of the underlying reader.
+    lines: Lines<R>,
+    /// The parsed header.
+    header: Header,
+    /// Number of the last read line, starting at 1.
+    line: usize,
+    /// Time of the previous event in microseconds (µs).
+    last: u128,
+    /// The file being read, which errors of events are attributed to.
+    file: Option<PathBuf>,
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Create a new reader, parsing the header from the first line.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the first line cannot be read, or is not a valid header.
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut lines = reader.lines();
+        let line = lines
+            .next()
+            .ok_or(ErrorType::MalformedHeader)
+            .and_then(|line| Ok(line?))
+            .map_err(|e| e.with_line(1))?;
+        let header = Header::parse(&line).map_err(|e| e.with_line(1))?;
+        Ok(Self {
+            lines,
+            header,
+            line: 1,
+            last: 0,
+            file: None,
+        })
+    }
+    /// Attribute errors of events to given file, e.g. to tell which of several asciicasts is malformed.
+    #[must_use]
+    pub fn file(mut self, file: impl Into<PathBuf>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+    /// Attach the line number and the file, if any, to an error.
+    fn error(&self, error: ErrorType) -> Error {
+        let error = error.with_line(self.line);
+        match &self.file {
+            Some(file) => error.in_file(file.clone()),
+            None => error,
+        }
+    }
+    /// The header of the asciicast.
+    pub const fn header(&self) -> &Header {
+        &self.header
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = Result<TimedEvent, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?;
+            self.line += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(self.error(ErrorType::Io(e)))),
+            };
+            let trimmed = line.trim();
+            if trimmed.is_empty() || (self.header.version >= 3 && trimmed.starts_with('#')) {
+                continue;
+            }
+            let event = TimedEvent::parse(trimmed, self.header.version, self.last)
+                .map_err(|e| self.error(e));
+            if let Ok(event) = &event {
+                self.last = event.time;
+            }
+            return Some(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Read all events of given asciicast.
+    fn events(cast: &str) -> Result<Vec<TimedEvent>, Error> {
+        Reader::new(cast.as_bytes())?.collect()
+    }
+
+    #[test]
+    fn read_v2() {
+        let cast = r#"{"version":2,"width":80,"height":24}
+[0.000000,"o","$ "]
+[0.100000,"i","l"]
+
+[1.000001,"m","marker"]
+[2.000000,"r","100x30"]
+"#;
+        let expected = [
+            (0, EventKind::Output("$ ".to_string())),
+            (100_000, EventKind::Input("l".to_string())),
+            (1_000_001, EventKind::Marker("marker".to_string())),
+            (2_000_000, EventKind::Resize(100, 30)),
+        ]
+        .map(|(time, kind)| TimedEvent { time, kind });
+        assert_eq!(events(cast).unwrap(), expected);
+    }
+
+    #[test]
+    fn read_v3() {
+        let cast = r#"{"version":3,"term":{"cols":80,"rows":24}}
+# A comment
+[0.100000,"o","Hello"]
+[0.200000,"o","\r\n"]
+[0.000000,"x","1"]
+"#;
+        let expected = [
+            (100_000, EventKind::Output("Hello".to_string())),
+            (300_000, EventKind::Output("\r\n".to_string())),
+            (300_000, EventKind::Exit(1)),
+        ]
+        .map(|(time, kind)| TimedEvent { time, kind });
+        assert_eq!(events(cast).unwrap(), expected);
+    }
+
+    #[test]
+    fn line_numbered_errors() {
+        let cases = [
+            ("", ErrorType::MalformedHeader, 1),
+            (r#"{"version":1}"#, ErrorType::UnsupportedVersion, 1),
+            (
+                "{\"version\":2,\"width\":80,\"height\":24}\n[0.1,\"o\",\"a\"]\n\n[0.2,\"o\"]",
+                ErrorType::MalformedEvent,
+                4,
+            ),
+            (
+                "{\"version\":2,\"width\":80,\"height\":24}\n[0.1,\"q\",\"a\"]",
+                ErrorType::MalformedEvent,
+                2,
+            ),
+            (
+                "{\"version\":2,\"width\":80,\"height\":24}\n[0.1,\"x\",\"0\"]",
+                ErrorType::MalformedEvent,
+                2,
+            ),
+            (
+                "{\"version\":2,\"width\":80,\"height\":24}\n[-1,\"o\",\"a\"]",
+                ErrorType::MalformedEvent,
+                2,
+            ),
+            (
+                "{\"version\":2,\"width\":80,\"height\":24}\n[0.1,\"r\",\"80\"]",
+                ErrorType::MalformedEvent,
+                2,
+            ),
+        ];
+        for (cast, error, line) in cases {
+            assert_eq!(
+                events(cast).unwrap_err(),
+                error.with_line(line),
+                "Cast: {cast}"
+            );
+        }
+
+        let cast = "{\"version\":2,\"width\":80,\"height\":24}\n[0.1,\"o\"]";
+        let error = Reader::new(cast.as_bytes())
+            .unwrap()
+            .file("demo.cast")
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.file(), Some(std::path::Path::new("demo.cast")));
+        assert_eq!(error.line, 2);
+    }
+}
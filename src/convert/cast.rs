@@ -0,0 +1,272 @@
+//! Module for importing asciicasts as `CastWright` scripts.
+
+use super::{escape_command, format_duration, format_loose_string, write_script};
+use crate::{Error, ErrorType, EventKind, Reader};
+use regex::Regex;
+use std::{
+    io::{BufRead, Write},
+    time::Duration,
+};
+
+/// The default prompt pattern, matching a line prefix without whitespace, ending with `$`, `#`, `%`, `>` or `❯` followed by a space.
+pub const DEFAULT_PROMPT: &str = r"^[^\s$#%>❯]*[$#%>❯] ";
+
+/// Import an asciicast as a `CastWright` script, with front matter and command instructions.
+///
+/// Commands are taken from the input events if the asciicast has any, or otherwise from the output lines that start with the `prompt` pattern (see [`DEFAULT_PROMPT`]). Outputs are left out, to be regenerated by executing the script. Commands ending with `\` are continued on following lines.
+///
+/// ## Errors
+///
+/// Returns an error if the prompt pattern is invalid, the asciicast is malformed, or writing fails.
+pub fn import_cast<R: BufRead, W: Write + ?Sized>(
+    reader: Reader<R>,
+    prompt: &str,
+    writer: &mut W,
+) -> Result<(), Error> {
+    let prompt = Regex::new(prompt).map_err(|e| ErrorType::Regex(e).with_line(0))?;
+    let header = reader.header().clone();
+
+    let mut input = Lines::new(true);
+    let mut output = Lines::new(false);
+    for event in reader {
+        match event?.kind {
+            EventKind::Input(data) => input.feed(&data),
+            EventKind::Output(data) => output.feed(&data),
+            _ => {}
+        }
+    }
+    let commands = if input.is_empty() {
+        commands(output.finish(), Some(&prompt))
+    } else {
+        commands(input.finish(), None)
+    };
+
+    let mut front_matter = vec![
+        format!("width: {}", header.width),
+        format!("height: {}", header.height),
+    ];
+    if let Some(title) = &header.title {
+        front_matter.push(format!("title: {}", format_loose_string(title)));
+    }
+    if let Some(idle) = header.idle_time_limit {
+        let idle = Duration::try_from_secs_f64(idle).unwrap_or_default();
+        front_matter.push(format!("idle: {}", format_duration(idle)));
+    }
+    let body: Vec<_> = commands
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let continued = index > 0 && commands[index - 1].ends_with('\\');
+            let instruction = if continued { '>' } else { '$' };
+            format!("{instruction} {}", escape_command(line))
+        })
+        .collect();
+    write_script(writer, &front_matter, &body)
+}
+
+/// Extract command lines from terminal lines, keeping the text after the prompt if a prompt pattern is given. A line following one ending with `\` is a continuation, and is kept even without a prompt.
+fn commands(lines: Vec<String>, prompt: Option<&Regex>) -> Vec<String> {
+    let mut commands: Vec<String> = Vec::new();
+    for line in lines {
+        let continued = commands.last().is_some_and(|last| last.ends_with('\\'));
+        let command = match prompt.and_then(|prompt| prompt.find(&line)) {
+            Some(found) => &line[found.end()..],
+            None if continued || prompt.is_none() => line.as_str(),
+            None => continue,
+        };
+        let command = command.trim();
+        if !command.is_empty() || continued {
+            commands.push(command.to_string());
+        }
+    }
+    commands
+}
+
+/// State of an escape sequence being skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    /// Not in an escape sequence.
+    None,
+    /// After `ESC`.
+    Start,
+    /// In a control sequence, after `ESC [`.
+    Csi,
+    /// In an operating system command, after `ESC ]`.
+    Osc,
+    /// After `ESC` in an operating system command, which might terminate it.
+    OscEsc,
+}
+
+/// Reconstruct lines of plain text from a stream of terminal data, skipping escape sequences and applying simple editing.
+struct Lines {
+    /// Whether the data is typed input, where `\r` ends a line and `^C`/`^U` discard it, instead of terminal output.
+    input: bool,
+    /// Finished lines.
+    finished: Vec<String>,
+    /// The current line.
+    line: String,
+    /// The escape sequence being skipped.
+    escape: Escape,
+    /// Whether the last character of output is a carriage return.
+    carriage: bool,
+}
+
+impl Lines {
+    /// Create an empty line buffer for input or output data.
+    const fn new(input: bool) -> Self {
+        Self {
+            input,
+            finished: Vec::new(),
+            line: String::new(),
+            escape: Escape::None,
+            carriage: false,
+        }
+    }
+
+    /// Whether no data has been fed.
+    const fn is_empty(&self) -> bool {
+        self.finished.is_empty() && self.line.is_empty()
+    }
+
+    /// Feed a chunk of data.
+    fn feed(&mut self, data: &str) {
+        for c in data.chars() {
+            self.escape = match (self.escape, c) {
+                (Escape::None, '\x1b') => Escape::Start,
+                (Escape::None, c) => {
+                    self.push(c);
+                    Escape::None
+                }
+                (Escape::Start, '[') => Escape::Csi,
+                (Escape::Start, ']') => Escape::Osc,
+                (Escape::Csi, '\x40'..='\x7e') | (Escape::Osc | Escape::OscEsc, '\x07') => {
+                    Escape::None
+                }
+                (Escape::OscEsc, '\\') | (Escape::Start, _) => Escape::None,
+                (Escape::Osc | Escape::OscEsc, '\x1b') => Escape::OscEsc,
+                (Escape::OscEsc, _) => Escape::Osc,
+                (state, _) => state,
+            };
+        }
+    }
+
+    /// Apply a character outside of escape sequences.
+    fn push(&mut self, c: char) {
+        let carriage = std::mem::take(&mut self.carriage);
+        match c {
+            '\r' if self.input => self.end(),
+            '\r' => self.carriage = true,
+            '\n' => self.end(),
+            '\x08' | '\x7f' => {
+                self.line.pop();
+            }
+            '\x03' | '\x15' if self.input => self.line.clear(),
+            c if c.is_control() => {}
+            c => {
+                // A lone carriage return in output moves back to overwrite the line
+                if carriage {
+                    self.line.clear();
+                }
+                self.line.push(c);
+            }
+        }
+    }
+
+    /// End the current line.
+    fn end(&mut self) {
+        self.finished.push(std::mem::take(&mut self.line));
+    }
+
+    /// Finish feeding, returning all lines including the unfinished one.
+    fn finish(mut self) -> Vec<String> {
+        if !self.line.is_empty() {
+            self.end();
+        }
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Import an asciicast from its text with given prompt.
+    fn import(cast: &str, prompt: &str) -> String {
+        let reader = Reader::new(cast.as_bytes()).unwrap();
+        let mut script = Vec::new();
+        import_cast(reader, prompt, &mut script).unwrap();
+        String::from_utf8(script).unwrap()
+    }
+
+    #[test]
+    fn commands_from_output() {
+        let cast = r#"{"version":2,"width":80,"height":24,"title":"Demo","idle_time_limit":1.5}
+[0.1,"o","\u001b[32muser@host\u001b[0m:~$ "]
+[0.2,"o","e"]
+[0.3,"o","c\bcho ${HOME} \\\r\n"]
+[0.4,"o","> world\r\n"]
+[0.5,"o","/home/user world\r\n"]
+[0.6,"o","user@host:~$ \r\n"]
+[0.7,"o","user@host:~$ ls\r\nfile\r\n"]
+[0.8,"o","user@host:~$ "]
+"#;
+        let expected = "---
+width: 80
+height: 24
+title: Demo
+idle: 1500ms
+---
+
+$ echo $${HOME} \\
+> world
+$ ls
+";
+        assert_eq!(import(cast, DEFAULT_PROMPT), expected);
+    }
+
+    #[test]
+    fn output_like_prompt() {
+        let cast = r#"{"version":2,"width":80,"height":24}
+[0.1,"o","$ make\r\nDownloading 50% done\r\nif a > b then\r\n"]
+[0.2,"o","checking 1 > 0 \r\n$ "]
+"#;
+        let expected = "---
+width: 80
+height: 24
+---
+
+$ make
+";
+        assert_eq!(import(cast, DEFAULT_PROMPT), expected);
+    }
+
+    #[test]
+    fn commands_from_input() {
+        let cast = r#"{"version":3,"term":{"cols":100,"rows":30}}
+[0.1,"o","% "]
+[0.1,"i","pwd\r"]
+[0.1,"o","pwd\r\n/root\r\n% "]
+[0.1,"i","oops\u0015git st\u001b[D\u001b[Cx\u007fatus\r"]
+"#;
+        let expected = "---
+width: 100
+height: 30
+---
+
+$ pwd
+$ git status
+";
+        assert_eq!(import(cast, DEFAULT_PROMPT), expected);
+    }
+
+    #[test]
+    fn custom_prompt() {
+        let cast = r#"{"version":2,"width":80,"height":24}
+[0.1,"o","λ whoami\r\nroot\r\n$ not a prompt\r\n"]
+"#;
+        assert!(import(cast, "^λ ").ends_with("---\n\n$ whoami\n"));
+        let reader = Reader::new(cast.as_bytes()).unwrap();
+        let error = import_cast(reader, "(", &mut Vec::new()).unwrap_err();
+        assert!(matches!(error.error, ErrorType::Regex(_)));
+    }
+}
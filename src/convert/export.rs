@@ -0,0 +1,153 @@
+//! Module for exporting `CastWright` scripts as POSIX shell scripts.
+
+use crate::{Error, ErrorType};
+use std::io::Write;
+
+/// A POSIX shell script, built up while a `CastWright` script is executed in export mode.
+#[derive(Debug, Default)]
+pub struct ShellScript {
+    /// Lines of the script body.
+    lines: Vec<String>,
+    /// Whether waits are exported as `sleep` commands instead of comments.
+    sleep: bool,
+}
+
+impl ShellScript {
+    /// Create an empty shell script.
+    pub const fn new(sleep: bool) -> Self {
+        Self {
+            lines: Vec::new(),
+            sleep,
+        }
+    }
+
+    /// Add a command. Unless it is expected to succeed, it is wrapped in a group followed by `|| true`, so that a trailing comment or a list of commands are guarded as a whole.
+    pub fn command(&mut self, command: &str, expect: Option<bool>) {
+        if expect == Some(true) {
+            self.lines.push(command.to_string());
+        } else {
+            self.lines.push("{".to_string());
+            self.lines.push(command.to_string());
+            self.lines.push("} || true".to_string());
+        }
+    }
+
+    /// Add a `printf` of a line of text, which unlike `echo` never interprets it.
+    pub fn print(&mut self, text: &str) {
+        self.lines.push(format!(r"printf '%s\n' {}", quote(text)));
+    }
+
+    /// Add a wait in microseconds (µs), as a comment or a `sleep` command. Since POSIX `sleep` only takes whole seconds, the latter is rounded up.
+    pub fn wait(&mut self, micros: u128) {
+        if self.sleep {
+            self.lines
+                .push(format!("sleep {}", micros.div_ceil(1_000_000)));
+        } else {
+            self.lines.push(format!("# Wait {}s", seconds(micros)));
+        }
+    }
+
+    /// Add a marker, as a comment.
+    pub fn marker(&mut self, label: &str) {
+        let comment = format!("# {}", label.replace('\n', " "));
+        self.lines.push(comment.trim_end().to_string());
+    }
+
+    /// Write the script, exiting on the first failing command like the recording does.
+    pub fn write<W: Write + ?Sized>(&self, writer: &mut W) -> Result<(), Error> {
+        let mut write = || -> std::io::Result<()> {
+            writeln!(writer, "#!/bin/sh")?;
+            writeln!(writer, "set -e")?;
+            writeln!(writer)?;
+            for line in &self.lines {
+                writeln!(writer, "{line}")?;
+            }
+            writer.flush()
+        };
+        write().map_err(|e| ErrorType::Io(e).with_line(0))
+    }
+}
+
+/// Quote a string for POSIX shells, in single quotes.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Format microseconds (µs) as seconds, without trailing zeros.
+fn seconds(micros: u128) -> String {
+    let fraction = format!("{:06}", micros % 1_000_000);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}", micros / 1_000_000)
+    } else {
+        format!("{}.{fraction}", micros / 1_000_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_script() {
+        let mut script = ShellScript::new(false);
+        script.marker("Setup");
+        script.command("cd /tmp", Some(true));
+        script.command("false", Some(false));
+        script.command("grep x file", None);
+        script.print("It's done");
+        script.wait(1_500_000);
+        let mut writer = Vec::new();
+        script.write(&mut writer).unwrap();
+        let expected = r"#!/bin/sh
+set -e
+
+# Setup
+cd /tmp
+{
+false
+} || true
+{
+grep x file
+} || true
+printf '%s\n' 'It'\''s done'
+# Wait 1.5s
+";
+        assert_eq!(String::from_utf8(writer).unwrap(), expected);
+
+        let mut script = ShellScript::new(true);
+        script.wait(2_000_000);
+        script.wait(10);
+        script.wait(0);
+        assert_eq!(script.lines, ["sleep 2", "sleep 1", "sleep 0"]);
+    }
+
+    #[test]
+    fn guarded_commands() {
+        let mut script = ShellScript::new(false);
+        // A trailing comment would swallow a `|| true` on the same line
+        script.command("false # fails", None);
+        // Only the last command of a list would be guarded
+        script.command("false && echo unreachable; false", Some(false));
+        assert_eq!(
+            script.lines,
+            [
+                "{",
+                "false # fails",
+                "} || true",
+                "{",
+                "false && echo unreachable; false",
+                "} || true",
+            ]
+        );
+
+        let mut writer = Vec::new();
+        script.write(&mut writer).unwrap();
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(String::from_utf8(writer).unwrap())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+}
@@ -0,0 +1,78 @@
+//! Module for converting between `CastWright` scripts and other formats.
+
+mod cast;
+mod export;
+mod tape;
+
+pub use cast::{DEFAULT_PROMPT, import_cast};
+pub use export::ShellScript;
+pub use tape::import_tape;
+
+use crate::{Error, ErrorType};
+use std::{io::Write, time::Duration};
+
+/// Write a `CastWright` script with given front matter and body lines, attributing io errors to line 0.
+fn write_script<W: Write + ?Sized>(
+    writer: &mut W,
+    front_matter: &[String],
+    body: &[String],
+) -> Result<(), Error> {
+    let mut write = || -> std::io::Result<()> {
+        writeln!(writer, "---")?;
+        for line in front_matter {
+            writeln!(writer, "{line}")?;
+        }
+        writeln!(writer, "---")?;
+        writeln!(writer)?;
+        for line in body {
+            writeln!(writer, "{line}")?;
+        }
+        writer.flush()
+    };
+    write().map_err(|e| ErrorType::Io(e).with_line(0))
+}
+
+/// Format a duration in the most precise unit that represents it exactly, as accepted by `CastWright` scripts.
+fn format_duration(duration: Duration) -> String {
+    let micros = duration.as_micros();
+    if micros.is_multiple_of(1_000_000) {
+        format!("{}s", micros / 1_000_000)
+    } else if micros.is_multiple_of(1_000) {
+        format!("{}ms", micros / 1_000)
+    } else {
+        format!("{micros}us")
+    }
+}
+
+/// Format a string so that it is parsed back as it is as a loose string.
+fn format_loose_string(s: &str) -> String {
+    if s.starts_with('"') || s.trim() != s {
+        serde_json::to_string(s).unwrap_or_default()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Escape `~{` and `${` in the text of a command, so that they are typed as they are, instead of being taken as mistakes or variables.
+fn escape_command(s: &str) -> String {
+    s.replace("~{", "~~{").replace("${", "$${")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format_duration(Duration::from_secs(2)), "2s");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1500ms");
+        assert_eq!(format_duration(Duration::from_micros(1)), "1us");
+        assert_eq!(format_loose_string("Demo"), "Demo");
+        assert_eq!(format_loose_string(" Demo"), r#"" Demo""#);
+        assert_eq!(format_loose_string(r#""Demo""#), r#""\"Demo\"""#);
+        assert_eq!(
+            escape_command("echo ${HOME} $PATH ~{x}"),
+            "echo $${HOME} $PATH ~~{x}"
+        );
+    }
+}
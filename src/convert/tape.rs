@@ -0,0 +1,524 @@
+//! Module for importing [VHS](https://github.com/charmbracelet/vhs) tapes as `CastWright` scripts.
+
+use super::{escape_command, format_duration, write_script};
+use crate::{Error, ErrorType};
+use std::{io::BufRead, io::Write, time::Duration};
+
+/// Settings that only affect how VHS renders the terminal, and are thus safely ignored.
+const COSMETIC_SETTINGS: [&str; 11] = [
+    "FontFamily",
+    "LetterSpacing",
+    "Theme",
+    "Framerate",
+    "LoopOffset",
+    "Margin",
+    "MarginFill",
+    "BorderRadius",
+    "WindowBar",
+    "WindowBarSize",
+    "CursorBlink",
+];
+
+/// Import a VHS tape as a `CastWright` script.
+///
+/// Typed text is gathered into a command until `Enter`, with erased text (`Backspace`) simulated as a mistake. `Sleep` becomes a wait, or the end lag of the command being typed. `Hide`/`Show` and `Set TypingSpeed` become `@@hidden` and `@@interval` (the latter after the command being typed, if any), while `Set Shell` becomes the shell in the front matter, and the terminal size is estimated from the `Width`, `Height`, `FontSize`, `Padding` and `LineHeight` settings. `Output` is kept as a comment, and `Wait` is dropped since commands are executed synchronously.
+///
+/// Commands without an equivalent (like `Ctrl+C` or `Tab`) are skipped and left as comments, and returned as errors with their line numbers.
+///
+/// ## Errors
+///
+/// Returns an error if a line of the tape is malformed, or reading or writing fails.
+pub fn import_tape<R: BufRead, W: Write + ?Sized>(
+    reader: R,
+    writer: &mut W,
+) -> Result<Vec<Error>, Error> {
+    let mut tape = Tape::default();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| ErrorType::Io(e).with_line(index + 1))?;
+        tape.parse(line.trim())
+            .map_err(|error| error.with_line(index + 1))?
+            .into_iter()
+            .for_each(|error| tape.unsupported(&line, error.with_line(index + 1)));
+    }
+    tape.flush();
+    tape.body.append(&mut tape.deferred);
+
+    let mut front_matter = vec![
+        format!("width: {}", tape.canvas.columns()),
+        format!("height: {}", tape.canvas.rows()),
+    ];
+    if let Some(shell) = &tape.shell {
+        let shell = serde_json::to_string(shell).map_err(|e| ErrorType::Json(e).with_line(0))?;
+        front_matter.push(format!("shell: {shell}"));
+    }
+    write_script(writer, &front_matter, &tape.body)?;
+    Ok(tape.unsupported)
+}
+
+/// Rendering settings of VHS, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Canvas {
+    width: f64,
+    height: f64,
+    font_size: f64,
+    padding: f64,
+    line_height: f64,
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self {
+            width: 1200.0,
+            height: 600.0,
+            font_size: 22.0,
+            padding: 60.0,
+            line_height: 1.0,
+        }
+    }
+}
+
+impl Canvas {
+    /// Estimated number of columns, taking the width of a monospace character as 0.6 of the font size.
+    fn columns(&self) -> u16 {
+        Self::cells(self.padding.mul_add(-2.0, self.width), self.font_size * 0.6)
+    }
+
+    /// Estimated number of rows.
+    fn rows(&self) -> u16 {
+        Self::cells(
+            self.padding.mul_add(-2.0, self.height),
+            self.font_size * self.line_height,
+        )
+    }
+
+    /// Number of cells of given size fitting in the length, at least one.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn cells(length: f64, cell: f64) -> u16 {
+        (length / cell).floor().clamp(1.0, f64::from(u16::MAX)) as u16
+    }
+}
+
+/// A part of the command being typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// Text that is kept.
+    Text(String),
+    /// Text that is typed and then erased.
+    Mistake(String),
+}
+
+/// A command being typed, not yet entered.
+#[derive(Debug, Default)]
+struct Pending {
+    segments: Vec<Segment>,
+    /// Typing interval given by `Type@<time>`.
+    interval: Option<Duration>,
+    /// Time slept before entering the command.
+    end_lag: Duration,
+}
+
+impl Pending {
+    /// Type given text.
+    fn type_text(&mut self, text: &str) {
+        if let Some(Segment::Text(last)) = self.segments.last_mut() {
+            last.push_str(text);
+        } else {
+            self.segments.push(Segment::Text(text.to_string()));
+        }
+    }
+
+    /// Erase given number of characters. They are taken as a mistake if they can be expressed as one, i.e. if they are typed right before and contain no braces. Otherwise, they are removed from the command without being shown.
+    fn erase(&mut self, count: usize) {
+        let Some(Segment::Text(last)) = self.segments.last_mut() else {
+            self.remove(count);
+            return;
+        };
+        let Some((split, _)) = last.char_indices().rev().nth(count.saturating_sub(1)) else {
+            self.remove(count);
+            return;
+        };
+        if count == 0 || last[split..].contains(['{', '}']) {
+            self.remove(count);
+            return;
+        }
+        let mistake = last.split_off(split);
+        if last.is_empty() {
+            self.segments.pop();
+        }
+        self.segments.push(Segment::Mistake(mistake));
+    }
+
+    /// Remove given number of characters from the kept text, without showing them.
+    fn remove(&mut self, mut count: usize) {
+        for segment in self.segments.iter_mut().rev() {
+            if let Segment::Text(text) = segment {
+                while count > 0 && text.pop().is_some() {
+                    count -= 1;
+                }
+            }
+        }
+        self.segments
+            .retain(|segment| !matches!(segment, Segment::Text(text) if text.is_empty()));
+    }
+
+    /// Render the command text, with mistakes wrapped in `~{` and `}`.
+    fn command(&self) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => escape_command(text),
+                Segment::Mistake(text) => format!("~{{{}}}", text.replace("${", "$${")),
+            })
+            .collect()
+    }
+
+    /// The kept text of the command.
+    fn text(&self) -> String {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Text(text) => Some(text.as_str()),
+                Segment::Mistake(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// State of importing a tape.
+#[derive(Debug, Default)]
+struct Tape {
+    /// Lines of the script body.
+    body: Vec<String>,
+    /// Rendering settings, to estimate the terminal size.
+    canvas: Canvas,
+    /// The command being typed.
+    pending: Option<Pending>,
+    /// Whether the output is hidden.
+    hidden: bool,
+    /// Whether the last command ends with `\`, so the next one continues it.
+    continuation: bool,
+    /// Configurations to be written once the command being typed is entered, along with its continuations.
+    deferred: Vec<String>,
+    /// The shell and its flags to execute commands with, if set.
+    shell: Option<Vec<String>>,
+    /// Unsupported commands.
+    unsupported: Vec<Error>,
+}
+
+impl Tape {
+    /// Parse a trimmed line of the tape, returning an error type if the command is unsupported.
+    fn parse(&mut self, line: &str) -> Result<Option<ErrorType>, ErrorType> {
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+        let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim();
+        // Commands may be suffixed with a speed, like `Type@500ms`
+        let (command, speed) = match command.split_once('@') {
+            Some((command, speed)) => (command, Some(parse_duration(speed)?)),
+            None => (command, None),
+        };
+        match command {
+            "Output" => self.body.push(format!("# Output {args}")),
+            "Set" => return self.set(args),
+            "Type" => {
+                let pending = self.pending.get_or_insert_default();
+                pending.type_text(&parse_string(args)?);
+                pending.interval = speed.or(pending.interval);
+            }
+            "Space" => {
+                let count = parse_count(args)?;
+                self.pending
+                    .get_or_insert_default()
+                    .type_text(&" ".repeat(count));
+            }
+            "Backspace" => {
+                let count = parse_count(args)?;
+                if let Some(pending) = &mut self.pending {
+                    pending.erase(count);
+                }
+            }
+            "Enter" => {
+                for _ in 0..parse_count(args)? {
+                    self.enter();
+                }
+            }
+            "Sleep" => {
+                let duration = parse_duration(args)?;
+                match &mut self.pending {
+                    Some(pending) => pending.end_lag += duration,
+                    None if !self.hidden => {
+                        self.body.push(format!("~ {}", format_duration(duration)));
+                    }
+                    None => {}
+                }
+            }
+            "Hide" | "Show" => {
+                self.flush();
+                self.hidden = command == "Hide";
+                let hidden = if self.hidden {
+                    "@@hidden"
+                } else {
+                    "@@hidden false"
+                };
+                self.body.push(hidden.to_string());
+            }
+            "Wait" | "Wait+Line" | "Wait+Screen" => {}
+            _ => return Ok(Some(ErrorType::UnsupportedTapeCommand(command.to_string()))),
+        }
+        Ok(None)
+    }
+
+    /// Apply a `Set` command.
+    fn set(&mut self, args: &str) -> Result<Option<ErrorType>, ErrorType> {
+        let (setting, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+        let value = value.trim();
+        let number = || {
+            value
+                .parse::<f64>()
+                .map_err(|_| ErrorType::MalformedInstruction)
+        };
+        match setting {
+            "Width" => self.canvas.width = number()?,
+            "Height" => self.canvas.height = number()?,
+            "FontSize" => self.canvas.font_size = number()?,
+            "Padding" => self.canvas.padding = number()?,
+            "LineHeight" => self.canvas.line_height = number()?,
+            "TypingSpeed" => {
+                let interval = format_duration(parse_duration(value)?);
+                let line = format!("@@interval {interval}");
+                // Configurations cannot be put in the middle of a command
+                if self.pending.is_some() || self.continuation {
+                    self.deferred.push(line);
+                } else {
+                    self.body.push(line);
+                }
+            }
+            "Shell" => {
+                let shell = value.trim_matches(['"', '\'', '`']);
+                if shell.is_empty() || shell.contains(char::is_whitespace) {
+                    return Err(ErrorType::MalformedInstruction);
+                }
+                self.shell = Some(shell_command(shell));
+            }
+            setting if COSMETIC_SETTINGS.contains(&setting) => {}
+            setting => {
+                return Ok(Some(ErrorType::UnsupportedTapeCommand(format!(
+                    "Set {setting}"
+                ))));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Press Enter, entering the command being typed, or an empty one.
+    fn enter(&mut self) {
+        let pending = self.pending.take().unwrap_or_default();
+        // Configurations cannot be put before continuations, and apply to the whole command anyway
+        let configurable = !self.continuation;
+        if let Some(interval) = pending.interval.filter(|_| configurable) {
+            self.body
+                .push(format!("@interval {}", format_duration(interval)));
+        }
+        if configurable && !pending.end_lag.is_zero() {
+            self.body
+                .push(format!("@end-lag {}", format_duration(pending.end_lag)));
+        }
+        let instruction = if self.continuation { '>' } else { '$' };
+        let command = pending.command();
+        self.continuation = pending.text().trim_end().ends_with('\\');
+        self.body
+            .push(format!("{instruction} {command}").trim_end().to_string());
+        if !self.continuation {
+            self.body.append(&mut self.deferred);
+        }
+    }
+
+    /// Enter the command being typed, if any.
+    fn flush(&mut self) {
+        if self.pending.is_some() {
+            self.enter();
+        }
+    }
+
+    /// Record an unsupported command, leaving it as a comment.
+    fn unsupported(&mut self, line: &str, error: Error) {
+        self.body.push(format!("# Unsupported: {}", line.trim()));
+        self.unsupported.push(error);
+    }
+}
+
+/// The command line to execute commands with given shell of VHS, i.e. the shell followed by its flags to run a command interactively.
+fn shell_command(shell: &str) -> Vec<String> {
+    let flags: &[&str] = match shell {
+        "cmd" => &["/C"],
+        "powershell" | "pwsh" => &["-Command"],
+        _ => &["-i", "-c"],
+    };
+    std::iter::once(shell)
+        .chain(flags.iter().copied())
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Parse a quoted string of VHS, delimited by `"`, `'` or `` ` ``.
+fn parse_string(s: &str) -> Result<String, ErrorType> {
+    let mut chars = s.chars();
+    let quote = chars.next().ok_or(ErrorType::MalformedInstruction)?;
+    if !matches!(quote, '"' | '\'' | '`') {
+        return Err(ErrorType::MalformedInstruction);
+    }
+    let s = chars.as_str();
+    match s.strip_suffix(quote) {
+        Some(inner) if !inner.contains(quote) => Ok(inner.to_string()),
+        _ => Err(ErrorType::MalformedInstruction),
+    }
+}
+
+/// Parse an optional repeat count, defaulting to 1.
+fn parse_count(s: &str) -> Result<usize, ErrorType> {
+    if s.is_empty() { Ok(1) } else { Ok(s.parse()?) }
+}
+
+/// Parse a duration of VHS, in `ms`, `s`, or seconds if without unit.
+fn parse_duration(s: &str) -> Result<Duration, ErrorType> {
+    let (number, scale) = s.strip_suffix("ms").map_or_else(
+        || (s.strip_suffix('s').unwrap_or(s), 1.0),
+        |ms| (ms, 1_000.0),
+    );
+    let number: f64 = number
+        .parse()
+        .map_err(|_| ErrorType::MalformedInstruction)?;
+    Duration::try_from_secs_f64(number / scale).map_err(|_| ErrorType::MalformedInstruction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Import a tape from its text, returning the script and the lines of unsupported commands.
+    fn import(tape: &str) -> (String, Vec<usize>) {
+        let mut script = Vec::new();
+        let unsupported = import_tape(tape.as_bytes(), &mut script).unwrap();
+        let lines = unsupported.iter().map(|error| error.line).collect();
+        (String::from_utf8(script).unwrap(), lines)
+    }
+
+    #[test]
+    fn tape_import() {
+        let tape = r#"# A demo tape
+Output demo.gif
+Set FontSize 20
+Set Width 1000
+Set Height 500
+Set Theme "Dracula"
+Set TypingSpeed 75ms
+
+Type "echo ${HOME} \"
+Enter
+Type "world"
+Enter
+Type@20ms "gti"
+Backspace 2
+Type "it status"
+Sleep 500ms
+Enter
+Sleep 2
+Hide
+Type "clear"
+Enter
+Sleep 1s
+Show
+Ctrl+C
+Enter 2
+"#;
+        let expected = r"---
+width: 73
+height: 19
+---
+
+# Output demo.gif
+@@interval 75ms
+$ echo $${HOME} \
+> world
+@interval 20ms
+@end-lag 500ms
+$ g~{ti}it status
+~ 2s
+@@hidden
+$ clear
+@@hidden false
+# Unsupported: Ctrl+C
+$
+$
+";
+        let (script, unsupported) = import(tape);
+        assert_eq!(script, expected);
+        assert_eq!(unsupported, [24]);
+    }
+
+    #[test]
+    fn erasing() {
+        let mut pending = Pending::default();
+        pending.type_text("ls {a}");
+        pending.erase(2);
+        pending.type_text("b}");
+        assert_eq!(pending.command(), "ls {b}");
+        pending.erase(10);
+        assert_eq!(pending.command(), "");
+    }
+
+    #[test]
+    fn tape_errors() {
+        let malformed = [
+            "Type unquoted",
+            "Type \"unclosed",
+            "Sleep forever",
+            "Enter twice",
+            "Set Width wide",
+            "Type@fast \"x\"",
+            "Set Shell",
+        ];
+        for line in malformed {
+            let error = import_tape(line.as_bytes(), &mut Vec::new()).unwrap_err();
+            assert_eq!(
+                error.error,
+                ErrorType::MalformedInstruction,
+                "Line: `{line}`"
+            );
+            assert_eq!(error.line, 1);
+        }
+        let (_, unsupported) = import("Tab\nSet PlaybackSpeed 2\nSet Shell \"zsh\"");
+        assert_eq!(unsupported, [1, 2]);
+    }
+
+    #[test]
+    fn shell_and_typing_speed() {
+        let tape = r#"Set Shell "zsh"
+Type "echo a \"
+Set TypingSpeed 50ms
+Enter
+Type "b"
+Enter
+Type "ls"
+Set TypingSpeed 10ms
+Enter
+"#;
+        let expected = r#"---
+width: 81
+height: 21
+shell: ["zsh","-i","-c"]
+---
+
+$ echo a \
+> b
+@@interval 50ms
+$ ls
+@@interval 10ms
+"#;
+        let (script, unsupported) = import(tape);
+        assert_eq!(script, expected);
+        assert!(unsupported.is_empty());
+        assert_eq!(shell_command("pwsh"), ["pwsh", "-Command"]);
+    }
+}
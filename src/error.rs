@@ -1,10 +1,16 @@
 //! Error types for the `castwright` crate.
 
+use pty_process::Error as PtyError;
+use regex::Error as RegexError;
 use serde_json::Error as JsonError;
 use std::io::Error as IoError;
-use std::{num::ParseIntError, time::SystemTimeError};
+use std::{
+    fmt,
+    num::ParseIntError,
+    path::{Path, PathBuf},
+    time::SystemTimeError,
+};
 use thiserror::Error as ThisError;
-use pty_process::Error as PtyError;
 
 /// Possible types of errors that can occur while parsing or executing a single line of a `CastWright` script. Each variant represents a specific type of error, and can be converted to an [`Error`] with the [`with_line`](`ErrorType::with_line`) method. (See the [`Error`] struct for examples)
 #[derive(ThisError, Debug)]
@@ -22,6 +28,9 @@ pub enum ErrorType {
     /// System time error.
     #[error("System time error: \"{0}\"")]
     SystemTime(SystemTimeError),
+    /// A `regex` error occurred while compiling a pattern.
+    #[error("Regex error: \"{0}\"")]
+    Regex(RegexError),
 
     // Front matter errors
     /// Expected key-value pair, but got instruction.
@@ -55,18 +64,60 @@ pub enum ErrorType {
     /// The front matter instruction is not recognized.
     #[error("Unknown front matter")]
     UnknownFrontMatter,
+    /// The script includes itself, directly or indirectly.
+    #[error("Circular include")]
+    CircularInclude,
+    /// The called macro is not defined.
+    #[error("Unknown macro")]
+    UnknownMacro,
+    /// The macro calls itself, directly or indirectly.
+    #[error("Recursive macro")]
+    RecursiveMacro,
+    /// Expected the end of a block, but got none.
+    #[error("Expected `@end`")]
+    ExpectedEnd,
+    /// Got the end of a block, but no block has been started.
+    #[error("Unexpected `@end`")]
+    UnexpectedEnd,
+    /// Got an alternative branch, but no conditional block has been started, or it already has one.
+    #[error("Unexpected `@else`")]
+    UnexpectedElse,
 
     // Asciicast errors
     /// The header has already been written.
     #[error("Header already written")]
     HeaderAlreadyWritten,
+    /// The version of the asciicast is not supported.
+    #[error("Unsupported asciicast version")]
+    UnsupportedVersion,
+    /// The header of the asciicast is not in the expected format.
+    #[error("Malformed asciicast header")]
+    MalformedHeader,
+    /// An event of the asciicast is not in the expected format.
+    #[error("Malformed asciicast event")]
+    MalformedEvent,
+    /// The marker with given label is not found in the asciicast.
+    #[error("Marker \"{0}\" not found")]
+    MarkerNotFound(String),
+    /// The speed factor is not a positive finite number.
+    #[error("Speed factor must be a positive finite number")]
+    InvalidSpeed,
+
+    // Import errors
+    /// The command of a VHS tape has no equivalent in `CastWright` scripts.
+    #[error("Unsupported tape command `{0}`")]
+    UnsupportedTapeCommand(String),
 }
 
 impl ErrorType {
     /// Add line number information to the error, so as to form a [`Error`].
     #[must_use]
     pub const fn with_line(self, line: usize) -> Error {
-        Error { error: self, line }
+        Error {
+            error: self,
+            line,
+            file: None,
+        }
     }
 }
 
@@ -94,6 +145,12 @@ impl From<SystemTimeError> for ErrorType {
     }
 }
 
+impl From<RegexError> for ErrorType {
+    fn from(error: RegexError) -> Self {
+        Self::Regex(error)
+    }
+}
+
 impl From<PtyError> for ErrorType {
     fn from(error: PtyError) -> Self {
         match error {
@@ -114,7 +171,7 @@ impl PartialEq for ErrorType {
 
 /// The `Error` struct represents an error that occurred during parsing or execution, with the line number denoting its position.
 ///
-/// Usually, you'll only need this struct in a function signature to propagate errors. To construct an `Error` manually, provide an [`ErrorType`] and a line number. Alternatively, you can call [`with_line`](`ErrorType::with_line`) on an [`ErrorType`] enum variant.
+/// Usually, you'll only need this struct in a function signature to propagate errors. To construct an `Error` manually, call [`with_line`](`ErrorType::with_line`) on an [`ErrorType`] enum variant. The struct is marked `#[non_exhaustive]`, so that more information can be attached to errors without breaking downstream code.
 ///
 /// ## Example
 ///
@@ -127,6 +184,7 @@ impl PartialEq for ErrorType {
 /// let error = error_type.with_line(1);
 /// assert!(matches!(error.error, ErrorType::UnknownInstruction));
 /// assert_eq!(error.line, 1);
+/// assert_eq!(error.file(), None);
 /// ```
 ///
 /// ### Propagating an error in `fn main`
@@ -148,7 +206,7 @@ impl PartialEq for ErrorType {
 ///     Ok(())
 /// }
 /// // Should get the following output:
-/// // Error: Error { error: UnexpectedContinuation, line: 2 }
+/// // Error: Error { error: UnexpectedContinuation, line: 2, file: None }
 /// ```
 ///
 /// ### Constructing an `Error` manually
@@ -159,22 +217,44 @@ impl PartialEq for ErrorType {
 /// fn main() -> Result<(), Error> {
 ///     let error_type = ErrorType::UnknownInstruction;
 ///     let error = error_type.with_line(1);
-///     // Or:
-///     // let error = Error {
-///     //     error: ErrorType::UnknownInstruction,
-///     //     line: 1,
-///     // };
 ///     Err(error)
 /// }
 /// // Should get the following output:
-/// // Error: Error { error: UnknownInstruction, line: 1 }
+/// // Error: Error { error: UnknownInstruction, line: 1, file: None }
 /// ```
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(ThisError, Debug)]
-#[error("{error} at line {line}")]
+#[non_exhaustive]
 pub struct Error {
     /// The type of error that occurred.
     pub error: ErrorType,
     /// The line number where the error occurred, starting at 1. If `0`, the error is not related to a specific line.
     pub line: usize,
+    /// The included script, or the asciicast file being read, where the error occurred. If `None`, the error occurred in the main script or input.
+    pub file: Option<PathBuf>,
+}
+
+impl Error {
+    /// The included script, or the asciicast file being read, where the error occurred. `None` if it occurred in the main script or input.
+    #[must_use]
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+    /// Attribute the error to given included script, if it has not been attributed to a (more deeply) included one.
+    pub(crate) fn in_file(mut self, file: PathBuf) -> Self {
+        if self.file.is_none() {
+            self.file = Some(file);
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}", self.error, self.line)?;
+        if let Some(file) = &self.file {
+            write!(f, " of {}", file.display())?;
+        }
+        Ok(())
+    }
 }
@@ -2,6 +2,10 @@
 
 use super::{
     AsciiCast, ErrorType, ExecutionContext, InstructionTrait, ParseContext, execute_command,
+    highlight::highlight,
+    prompt::render,
+    typing::{strip_mistakes, type_command},
+    util,
 };
 use std::io::Write;
 
@@ -59,104 +63,181 @@ impl InstructionTrait for CommandInstruction {
         let temp = context.temporary.get(!self.continuation);
         let config = context.persistent.combine(temp);
 
+        let typed = context.interpolate(&self.command);
+        let command = strip_mistakes(&typed);
+
+        if let Some(script) = &mut context.export {
+            // Export the full command, hidden or not, instead of typing and executing it
+            if self.continuation {
+                context.command.push_str(&command);
+                context.command.push(' ');
+            } else {
+                let mut full_command = std::mem::take(&mut context.command);
+                full_command.push_str(&command);
+                script.command(&full_command, config.expect);
+            }
+            return Ok(());
+        }
+
         if config.hidden {
             if context.execute {
                 // Execute command silently
                 let expect = config.expect;
-                let reader = execute_command(context, &self.command)?;
+                let mut reader = execute_command(context, &command)?;
                 let result = || -> Result<(), ErrorType> {
-                    for chunk in reader {
+                    for chunk in &mut reader {
                         // Discard the output
                         chunk?;
                     }
                     Ok(())
                 }();
+                context.status = reader.code();
                 handle_error(result, expect)?;
             }
             return Ok(());
         }
 
-        let prompt = if self.start {
+        let prompt = context.interpolate(if self.start {
             &config.prompt
         } else {
             &config.secondary_prompt
+        });
+        let prompt = render(&prompt, context);
+        let interval = config.typing_interval();
+        let typed = if config.highlight {
+            highlight(&typed, &context.theme)
+        } else {
+            typed
         };
-        let interval = config.interval;
-        cast.output(context.elapsed, prompt)?;
-        context.preview(prompt);
+        cast.output(context.elapsed, &prompt)?;
+        context.preview(&prompt);
         context.elapsed += config.start_lag;
 
-        if interval > 0 {
-            for character in self.command.chars() {
-                context.elapsed += interval;
-                // https://stackoverflow.com/a/67898224/16468609
-                cast.output(context.elapsed, character.encode_utf8(&mut [0u8; 4]))?;
+        if let Some(bracketed) = config.paste {
+            // Paste the command at once
+            if context.record_input {
+                if bracketed {
+                    let pasted = format!("\x1b[200~{command}\x1b[201~");
+                    cast.input(context.elapsed, &pasted)?;
+                } else {
+                    cast.input(context.elapsed, &command)?;
+                }
+            }
+            cast.output(context.elapsed, &strip_mistakes(&typed))?;
+            context.preview(&command);
+            if self.continuation {
+                record_continuation(context, cast)?;
+                cast.output(context.elapsed, &config.line_continuation)?;
+                context.preview(&config.line_continuation);
             }
+            context.elapsed += config.paste_pause;
         } else {
-            cast.output(context.elapsed, &self.command)?;
-        }
-        context.preview(&self.command);
-
-        context.elapsed += interval;
-        if self.continuation {
-            cast.output(context.elapsed, &config.line_continuation)?;
-            context.preview(&config.line_continuation);
+            // A temporary seed applies to this command only
+            let mut seeded = config.seed.map(util::Rng::new);
+            type_command(
+                &typed,
+                &config,
+                &mut context.elapsed,
+                seeded.as_mut().unwrap_or(&mut context.rng),
+                cast,
+                context.record_input,
+            )?;
+            context.preview(&command);
 
             context.elapsed += interval;
-            context.elapsed += config.end_lag;
-            cast.output(context.elapsed, "\r\n")?;
-            context.preview("\r\n");
+            if self.continuation {
+                record_continuation(context, cast)?;
+                cast.output(context.elapsed, &config.line_continuation)?;
+                context.preview(&config.line_continuation);
+                context.elapsed += interval;
+            }
+        }
+
+        context.elapsed += config.end_lag;
+        if context.record_input {
+            // The Enter key
+            cast.input(context.elapsed, "\r")?;
+        }
+        cast.output(context.elapsed, "\r\n")?;
+        context.preview("\r\n");
 
-            context.command.push_str(&self.command);
+        if self.continuation {
+            context.command.push_str(&command);
             context.command.push(' ');
         } else {
-            context.elapsed += config.end_lag;
-            cast.output(context.elapsed, "\r\n")?;
-            context.preview("\r\n");
-
             // Take `context.command` out, replacing with an empty string
-            let mut command = std::mem::take(&mut context.command);
-            command.push_str(&self.command);
+            let mut full_command = std::mem::take(&mut context.command);
+            full_command.push_str(&command);
+            let command = full_command;
 
             if context.execute {
-                // Make borrow checker happy
-                let preview = context.preview;
-                let mut elapsed = context.elapsed;
+                let indicator = config.status_indicator.clone();
+                capture_command(context, cast, &command, config.expect, &indicator)?;
+            }
+        }
 
-                let expect = config.expect;
-                let mut prev = std::time::Instant::now();
-                let reader = execute_command(context, &command)?;
-                let mut lock = std::io::stdout().lock();
+        Ok(())
+    }
+}
 
-                let result = || -> Result<(), ErrorType> {
-                    for chunk in reader {
-                        let Some(chunk) = chunk? else {
-                            // No output yet, but command is still running
-                            continue;
-                        };
-                        let now = std::time::Instant::now();
-                        elapsed += now.duration_since(prev).as_micros();
-                        prev = now;
-
-                        cast.output(elapsed, &chunk)?;
-                        // context.preview(&chunk);
-                        // 1. Ensure that the output is flushed in real-time
-                        // 2. Use lock to improve performance in case there are many chunks
-                        if preview {
-                            print!("{chunk}");
-                            lock.flush()?;
-                        }
-                    }
-                    Ok(())
-                }();
-                context.elapsed = elapsed;
+/// Execute given command, capturing its output to the asciicast in real time. If the command exits with a non-zero status, given status indicator is rendered and printed as a line.
+fn capture_command(
+    context: &mut ExecutionContext,
+    cast: &mut AsciiCast<impl std::io::Write>,
+    command: &str,
+    expect: Option<bool>,
+    indicator: &str,
+) -> Result<(), ErrorType> {
+    // Make borrow checker happy
+    let preview = context.preview;
+    let mut elapsed = context.elapsed;
 
-                handle_error(result, expect)?;
+    let mut prev = std::time::Instant::now();
+    let mut reader = execute_command(context, command)?;
+    let mut lock = std::io::stdout().lock();
+
+    let result = || -> Result<(), ErrorType> {
+        for chunk in &mut reader {
+            let Some(chunk) = chunk? else {
+                // No output yet, but command is still running
+                continue;
+            };
+            let now = std::time::Instant::now();
+            elapsed += now.duration_since(prev).as_micros();
+            prev = now;
+
+            cast.output(elapsed, &chunk)?;
+            // context.preview(&chunk);
+            // 1. Ensure that the output is flushed in real-time
+            // 2. Use lock to improve performance in case there are many chunks
+            if preview {
+                print!("{chunk}");
+                lock.flush()?;
             }
         }
-
         Ok(())
+    }();
+    context.elapsed = elapsed;
+    context.status = reader.code();
+
+    if context.status != 0 && !indicator.is_empty() {
+        let line = format!("{}\r\n", render(indicator, context));
+        cast.output(context.elapsed, &line)?;
+        context.preview(&line);
+    }
+
+    handle_error(result, expect)
+}
+
+/// Record the keystrokes of a line continuation (` \\`) as an input event, if enabled.
+fn record_continuation(
+    context: &ExecutionContext,
+    cast: &mut AsciiCast<impl std::io::Write>,
+) -> Result<(), ErrorType> {
+    if context.record_input {
+        cast.input(context.elapsed, " \\")?;
     }
+    Ok(())
 }
 
 /// Handle the result of executing a command (see if it fulfills the expectation).
@@ -227,6 +308,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn paste_input() {
+        let cases = [
+            (false, false, vec![]),
+            (true, false, vec![]),
+            (false, true, vec![r#""ls""#, r#""\r""#]),
+            (true, true, vec![r#""\u001b[200~ls\u001b[201~""#, r#""\r""#]),
+        ];
+        for (bracketed, record_input, expected) in cases {
+            let mut parse_context = ParseContext::new();
+            parse_context.start = '$';
+            let mut context = ExecutionContext::new();
+            context.persistent.paste = Some(bracketed);
+            context.record_input = record_input;
+
+            let mut writer = Vec::new();
+            let mut cast = AsciiCast::new(&mut writer);
+            CommandInstruction::parse("ls", &mut parse_context)
+                .unwrap()
+                .execute(&mut context, &mut cast)
+                .unwrap();
+            drop(cast);
+
+            let output = String::from_utf8(writer).unwrap();
+            let inputs: Vec<&str> = output
+                .lines()
+                .filter_map(|line| line.split_once(r#","i","#))
+                .map(|(_, data)| data.trim_end_matches(']'))
+                .collect();
+            assert_eq!(
+                inputs, expected,
+                "Bracketed: {bracketed}, record input: {record_input}"
+            );
+        }
+    }
+
+    #[test]
+    fn status_indicator() {
+        let mut parse_context = ParseContext::new();
+        parse_context.start = '$';
+        let mut context = ExecutionContext::new();
+        context.execute = true;
+        context.persistent.interval = Some(0);
+        context.persistent.expect = None;
+        context.persistent.status_indicator = "✘ {status}".to_string();
+
+        let mut writer = Vec::new();
+        let mut cast = AsciiCast::new(&mut writer);
+        for command in ["exit 2", "true"] {
+            CommandInstruction::parse(command, &mut parse_context)
+                .unwrap()
+                .execute(&mut context, &mut cast)
+                .unwrap();
+        }
+        drop(cast);
+
+        let output = String::from_utf8(writer).unwrap();
+        assert_eq!(output.matches("✘").count(), 1, "Output: {output}");
+        assert!(output.contains(r#""✘ 2\r\n""#), "Output: {output}");
+        assert_eq!(context.status, 0);
+    }
+
     #[test]
     fn error_handling() {
         let should_succeed: [(Result<(), ErrorType>, Option<_>); 4] = [
@@ -0,0 +1,285 @@
+//! Module for conditional instructions.
+
+use super::{AsciiCast, ErrorType, ExecutionContext, InstructionTrait, ParseContext, util};
+use std::path::Path;
+
+/// A condition to be evaluated at execution time.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Condition {
+    /// The environment variable is set.
+    Env(String),
+    /// The command can be found in `PATH`.
+    CommandExists(String),
+    /// The file or directory exists, relative to the working directory.
+    FileExists(String),
+    /// The operating system (like `linux`) or its family (like `unix`) matches.
+    Os(String),
+}
+
+impl Condition {
+    /// Parse a condition, like `env HOME`.
+    fn parse(s: &str) -> Result<Self, ErrorType> {
+        let (kind, argument) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let argument = util::parse_loose_string(argument.trim())?;
+        if argument.is_empty() {
+            return Err(ErrorType::MalformedInstruction);
+        }
+        match kind {
+            "env" => Ok(Self::Env(argument)),
+            "command-exists" => Ok(Self::CommandExists(argument)),
+            "file-exists" => Ok(Self::FileExists(argument)),
+            "os" => Ok(Self::Os(argument)),
+            _ => Err(ErrorType::MalformedInstruction),
+        }
+    }
+    /// Evaluate the condition. Variables in the argument are interpolated beforehand.
+    fn evaluate(&self, context: &ExecutionContext) -> bool {
+        match self {
+            Self::Env(name) => std::env::var_os(context.interpolate(name)).is_some(),
+            Self::CommandExists(name) => command_exists(&context.interpolate(name), context),
+            Self::FileExists(path) => context.directory.join(context.interpolate(path)).exists(),
+            Self::Os(os) => {
+                let os = context.interpolate(os);
+                os == std::env::consts::OS || os == std::env::consts::FAMILY
+            }
+        }
+    }
+}
+
+/// A conditional instruction.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConditionalInstruction {
+    /// Start of a conditional block, with the condition and whether it is negated.
+    If(Condition, bool),
+    /// Start of the alternative branch.
+    Else,
+    /// End of a conditional block.
+    End,
+}
+
+impl ConditionalInstruction {
+    /// Check if a trimmed line (without the leading `@`) is a conditional instruction.
+    pub fn matches(s: &str) -> bool {
+        matches!(s.split_whitespace().next(), Some("if" | "else" | "end"))
+    }
+}
+
+impl InstructionTrait for ConditionalInstruction {
+    /// Parse a trimmed line (without the leading `@`) into a `ConditionalInstruction`.
+    fn parse(s: &str, context: &mut ParseContext) -> Result<Self, ErrorType> {
+        context.front_matter_state.end()?;
+        if context.expect_continuation {
+            return Err(ErrorType::ExpectedContinuation);
+        }
+
+        let (keyword, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let rest = rest.trim();
+        match keyword {
+            "if" => {
+                let (negated, condition) = match rest.strip_prefix("not") {
+                    Some(condition) if condition.starts_with(char::is_whitespace) => {
+                        (true, condition.trim())
+                    }
+                    _ => (false, rest),
+                };
+                let condition = Condition::parse(condition)?;
+                context.conditionals.push(false);
+                Ok(Self::If(condition, negated))
+            }
+            "else" | "end" if !rest.is_empty() => Err(ErrorType::MalformedInstruction),
+            "else" => {
+                let has_else = context
+                    .conditionals
+                    .last_mut()
+                    .ok_or(ErrorType::UnexpectedElse)?;
+                if *has_else {
+                    return Err(ErrorType::UnexpectedElse);
+                }
+                *has_else = true;
+                Ok(Self::Else)
+            }
+            "end" => {
+                context.conditionals.pop().ok_or(ErrorType::UnexpectedEnd)?;
+                Ok(Self::End)
+            }
+            _ => Err(ErrorType::UnknownInstruction),
+        }
+    }
+    /// Execute the instruction, updating the branches in the execution context.
+    fn execute(
+        &self,
+        context: &mut ExecutionContext,
+        _cast: &mut AsciiCast<impl std::io::Write>,
+    ) -> Result<(), ErrorType> {
+        match self {
+            Self::If(condition, negated) => {
+                // Only evaluate the condition if the enclosing branch is active
+                let active = !context.skipping() && condition.evaluate(context) != *negated;
+                context.branches.push(active);
+            }
+            Self::Else => {
+                let active = context.branches.pop().expect("Checked when parsing");
+                let active = !context.skipping() && !active;
+                context.branches.push(active);
+            }
+            Self::End => {
+                context.branches.pop().expect("Checked when parsing");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check if a command can be found, either as a path relative to the working directory or in `PATH`.
+fn command_exists(name: &str, context: &ExecutionContext) -> bool {
+    if name.contains(std::path::MAIN_SEPARATOR) || name.contains('/') {
+        return is_executable(&context.directory.join(name));
+    }
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| {
+            let path = dir.join(name);
+            is_executable(&path) || (cfg!(windows) && is_executable(&path.with_extension("exe")))
+        })
+    })
+}
+
+/// Check if a path is an executable file.
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditional_instruction() {
+        let mut context = ParseContext::new();
+        let instructions = [
+            (
+                "if env HOME",
+                ConditionalInstruction::If(Condition::Env("HOME".to_string()), false),
+            ),
+            (
+                "if not command-exists \"my tool\"",
+                ConditionalInstruction::If(Condition::CommandExists("my tool".to_string()), true),
+            ),
+            ("else", ConditionalInstruction::Else),
+            (
+                "if file-exists Cargo.toml",
+                ConditionalInstruction::If(Condition::FileExists("Cargo.toml".to_string()), false),
+            ),
+            ("end", ConditionalInstruction::End),
+            (
+                "if os notlinux",
+                ConditionalInstruction::If(Condition::Os("notlinux".to_string()), false),
+            ),
+            ("end", ConditionalInstruction::End),
+            ("end", ConditionalInstruction::End),
+            ("end", ConditionalInstruction::End),
+        ];
+        for (line, expected) in &instructions {
+            assert_eq!(
+                &ConditionalInstruction::parse(line, &mut context).unwrap(),
+                expected
+            );
+        }
+        assert!(context.conditionals.is_empty());
+    }
+
+    #[test]
+    fn malformed_conditional_instruction() {
+        let cases = [
+            (vec!["if"], ErrorType::MalformedInstruction),
+            (vec!["if env"], ErrorType::MalformedInstruction),
+            (vec!["if unknown HOME"], ErrorType::MalformedInstruction),
+            (
+                vec!["if env HOME", "else what"],
+                ErrorType::MalformedInstruction,
+            ),
+            (vec!["else"], ErrorType::UnexpectedElse),
+            (
+                vec!["if env HOME", "else", "else"],
+                ErrorType::UnexpectedElse,
+            ),
+            (vec!["end"], ErrorType::UnexpectedEnd),
+        ];
+        for (lines, expected) in cases {
+            let mut context = ParseContext::new();
+            let err = lines
+                .iter()
+                .try_for_each(|line| ConditionalInstruction::parse(line, &mut context).map(drop))
+                .unwrap_err();
+            assert_eq!(err, expected, "Lines: {lines:?}");
+        }
+    }
+
+    #[test]
+    fn evaluate_condition() {
+        let context = ExecutionContext::new();
+        let cases = [
+            ("env PATH", true),
+            ("env CASTWRIGHT_UNDEFINED_VARIABLE", false),
+            ("file-exists Cargo.toml", true),
+            ("file-exists src", true),
+            ("file-exists not-found.txt", false),
+            ("command-exists castwright-command-not-found", false),
+            ("os windows-or-linux", false),
+        ];
+        for (condition, expected) in cases {
+            let parsed = Condition::parse(condition).unwrap();
+            assert_eq!(
+                parsed.evaluate(&context),
+                expected,
+                "Condition: `{condition}`"
+            );
+        }
+        let os = Condition::Os(std::env::consts::OS.to_string());
+        assert!(os.evaluate(&context));
+        let family = Condition::Os(std::env::consts::FAMILY.to_string());
+        assert!(family.evaluate(&context));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn evaluate_command_exists() {
+        let context = ExecutionContext::new();
+        assert!(Condition::CommandExists("sh".to_string()).evaluate(&context));
+        assert!(!Condition::CommandExists("Cargo.toml".to_string()).evaluate(&context));
+    }
+
+    #[test]
+    fn execute_branches() {
+        let mut parse_context = ParseContext::new();
+        let mut context = ExecutionContext::new();
+        let sink = &mut std::io::sink(); // Drop all output
+        let mut cast = AsciiCast::new(sink);
+        let lines = [
+            ("if env PATH", vec![true]),
+            ("if not env PATH", vec![true, false]),
+            ("if env PATH", vec![true, false, false]),
+            ("else", vec![true, false, false]),
+            ("end", vec![true, false]),
+            ("else", vec![true, true]),
+            ("end", vec![true]),
+            ("else", vec![false]),
+            ("end", vec![]),
+        ];
+        for (line, expected) in lines {
+            ConditionalInstruction::parse(line, &mut parse_context)
+                .unwrap()
+                .execute(&mut context, &mut cast)
+                .unwrap();
+            assert_eq!(context.branches, expected, "Line: `{line}`");
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! Module for config instructions.
 
-use super::{AsciiCast, ErrorType, ExecutionContext, InstructionTrait, ParseContext, util};
+use super::{AsciiCast, ErrorType, ExecutionContext, InstructionTrait, Pace, ParseContext, util};
 
 /// A configuration instruction type.
 #[derive(Debug, PartialEq)]
@@ -15,12 +15,32 @@ enum ConfigInstructionType {
     Hidden(bool),
     /// Expected exit status of the command. `true` for success, `false` for failure, `None` for any.
     Expect(Option<bool>),
-    /// Typing interval between characters in a command or print instruction, in microseconds (µs).
+    /// Typing interval between characters in a command or print instruction, in microseconds (µs). Takes precedence over `Wpm`.
     Interval(u128),
     /// The start lag in microseconds (µs). i.e. Additional delay after displaying the prompt, before printing the command for command instructions, or before printing the content for print instructions.
     StartLag(u128),
     /// The end lag in microseconds (µs). i.e. Additional delay after printing the command for command instructions, or after printing the content for print instructions.
     EndLag(u128),
+    /// Maximum random variation of the typing interval, in percent of the interval.
+    Jitter(u8),
+    /// Additional delay after typing a whitespace or punctuation character, in microseconds (µs).
+    Pause(u128),
+    /// Probability of mistyping a letter in a command, in percent, and optionally a new seed for the random number generator. A temporary seed only applies to the next instruction.
+    Typos(u8, Option<u64>),
+    /// Typing speed in words per minute. Has no effect if an `Interval` is set.
+    Wpm(u16),
+    /// Whether to paste commands at once (`None` for typing, `Some(true)` for bracketed paste), and optionally the delay before pressing Enter in microseconds (µs).
+    Paste(Option<bool>, Option<u128>),
+    /// Whether to highlight the shell syntax of commands.
+    Highlight(bool),
+    /// Template of the line to print after a command exits with a non-zero status.
+    StatusIndicator(String),
+    /// Additional delay after each line of a print block but the last, in microseconds (µs).
+    LinePause(u128),
+    /// How the content of print instructions is revealed.
+    PrintPace(Pace),
+    /// Define a variable, with its name and value. Can only be persistent.
+    Set(String, String),
 }
 
 /// A configuration instruction.
@@ -30,75 +50,125 @@ pub struct ConfigInstruction {
     persistent: bool,
 }
 
-impl InstructionTrait for ConfigInstruction {
-    /// Parse a trimmed line into a `ConfigInstruction`.
-    fn parse(s: &str, context: &mut ParseContext) -> Result<Self, ErrorType> {
-        context.front_matter_state.end()?;
-        if context.expect_continuation {
-            return Err(ErrorType::ExpectedContinuation);
-        }
-
-        let s = s.trim();
-        // The first character ('@') has been removed, thus the check is for the second character
-        let persistent = s.starts_with('@');
-        let s = if persistent { &s[1..] } else { s }; // Remove the '@' if it's present
+impl ConfigInstructionType {
+    /// Parse the type of a configuration instruction, with the leading `@`s removed.
+    fn parse(s: &str, persistent: bool) -> Result<Self, ErrorType> {
         let mut iter = s.split_whitespace();
         let Some(first) = iter.next() else {
             return Err(ErrorType::MalformedInstruction);
         };
 
         let len = first.len();
-        let instruction_type = match first {
-            "prompt" => {
-                let prompt = util::parse_loose_string(s[len..].trim())?;
-                Ok(ConfigInstructionType::Prompt(prompt))
-            }
-            "secondary" | "secondary-prompt" => {
-                let prompt = util::parse_loose_string(s[len..].trim())?;
-                Ok(ConfigInstructionType::SecondaryPrompt(prompt))
-            }
-            "continuation" | "line-continuation" => {
-                let split = util::parse_loose_string(s[len..].trim())?;
-                Ok(ConfigInstructionType::LineContinuation(split))
-            }
-            "hidden" => {
-                let hidden = iter.next();
-                hidden.map_or(Ok(ConfigInstructionType::Hidden(true)), |word| match word {
-                    "true" => Ok(ConfigInstructionType::Hidden(true)),
-                    "false" => Ok(ConfigInstructionType::Hidden(false)),
-                    _ => Err(ErrorType::MalformedInstruction),
-                })
-            }
+        // The rest of the line, as a loose string
+        let loose = || util::parse_loose_string(s[len..].trim());
+        match first {
+            "prompt" => Ok(Self::Prompt(loose()?)),
+            "secondary" | "secondary-prompt" => Ok(Self::SecondaryPrompt(loose()?)),
+            "continuation" | "line-continuation" => Ok(Self::LineContinuation(loose()?)),
+            "hidden" => Ok(Self::Hidden(next_bool(&mut iter)?)),
             "expect" => {
                 let expect = iter.next();
                 let word = expect.unwrap_or("success");
                 match word {
-                    "success" => Ok(ConfigInstructionType::Expect(Some(true))),
-                    "failure" => Ok(ConfigInstructionType::Expect(Some(false))),
-                    "any" => Ok(ConfigInstructionType::Expect(None)),
+                    "success" => Ok(Self::Expect(Some(true))),
+                    "failure" => Ok(Self::Expect(Some(false))),
+                    "any" => Ok(Self::Expect(None)),
                     _ => Err(ErrorType::MalformedInstruction),
                 }
             }
-            "interval" => {
-                let interval = iter.next().ok_or(ErrorType::MalformedInstruction)?;
-                Ok(ConfigInstructionType::Interval(
-                    util::parse_duration(interval)?.as_micros(),
-                ))
+            "interval" => Ok(Self::Interval(next_duration(&mut iter)?)),
+            "start-lag" => Ok(Self::StartLag(next_duration(&mut iter)?)),
+            "end-lag" => Ok(Self::EndLag(next_duration(&mut iter)?)),
+            "jitter" => {
+                let jitter = iter.next().ok_or(ErrorType::MalformedInstruction)?;
+                Ok(Self::Jitter(util::parse_percentage(jitter)?))
+            }
+            "pause" => Ok(Self::Pause(next_duration(&mut iter)?)),
+            "line-pause" => Ok(Self::LinePause(next_duration(&mut iter)?)),
+            "print-pace" => Ok(Self::PrintPace(Pace::parse(iter)?)),
+            "typos" => {
+                let typos = iter.next().ok_or(ErrorType::MalformedInstruction)?;
+                let typos = util::parse_percentage(typos)?;
+                let seed = iter
+                    .next()
+                    .map(|seed| seed.parse().map_err(|_| ErrorType::MalformedInstruction))
+                    .transpose()?;
+                if iter.next().is_some() {
+                    return Err(ErrorType::MalformedInstruction);
+                }
+                Ok(Self::Typos(typos, seed))
+            }
+            "wpm" => {
+                let wpm = iter.next().ok_or(ErrorType::MalformedInstruction)?;
+                match wpm.parse() {
+                    Ok(wpm) if wpm > 0 => Ok(Self::Wpm(wpm)),
+                    _ => Err(ErrorType::MalformedInstruction),
+                }
             }
-            "start-lag" => {
-                let delay = iter.next().ok_or(ErrorType::MalformedInstruction)?;
-                Ok(ConfigInstructionType::StartLag(
-                    util::parse_duration(delay)?.as_micros(),
-                ))
+            "paste" => {
+                let paste = match iter.next() {
+                    None | Some("true") => Some(false),
+                    Some("false") => None,
+                    Some("bracketed") => Some(true),
+                    _ => return Err(ErrorType::MalformedInstruction),
+                };
+                let pause = iter
+                    .next()
+                    .map(|pause| util::parse_duration(pause).map(|d| d.as_micros()))
+                    .transpose()?;
+                if iter.next().is_some() {
+                    return Err(ErrorType::MalformedInstruction);
+                }
+                Ok(Self::Paste(paste, pause))
             }
-            "end-lag" => {
-                let delay = iter.next().ok_or(ErrorType::MalformedInstruction)?;
-                Ok(ConfigInstructionType::EndLag(
-                    util::parse_duration(delay)?.as_micros(),
-                ))
+            "highlight" => Ok(Self::Highlight(next_bool(&mut iter)?)),
+            "status-indicator" => Ok(Self::StatusIndicator(loose()?)),
+            "set" => {
+                // Variables are not bound to a single instruction, so they can only be persistent
+                if !persistent {
+                    return Err(ErrorType::MalformedInstruction);
+                }
+                let name = iter.next().ok_or(ErrorType::MalformedInstruction)?;
+                if !util::is_valid_variable_name(name) {
+                    return Err(ErrorType::MalformedInstruction);
+                }
+                let rest = s[len..].trim_start();
+                let value = util::parse_loose_string(rest[name.len()..].trim())?;
+                Ok(Self::Set(name.to_string(), value))
             }
             _ => Err(ErrorType::UnknownConfig),
-        }?;
+        }
+    }
+}
+
+/// Parse the next word as a boolean, defaulting to `true` if omitted.
+fn next_bool<'a>(iter: &mut impl Iterator<Item = &'a str>) -> Result<bool, ErrorType> {
+    match iter.next() {
+        None | Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        _ => Err(ErrorType::MalformedInstruction),
+    }
+}
+
+/// Parse the next word as a [Duration](util::parse_duration), in microseconds (µs).
+fn next_duration<'a>(iter: &mut impl Iterator<Item = &'a str>) -> Result<u128, ErrorType> {
+    let duration = iter.next().ok_or(ErrorType::MalformedInstruction)?;
+    Ok(util::parse_duration(duration)?.as_micros())
+}
+
+impl InstructionTrait for ConfigInstruction {
+    /// Parse a trimmed line into a `ConfigInstruction`.
+    fn parse(s: &str, context: &mut ParseContext) -> Result<Self, ErrorType> {
+        context.front_matter_state.end()?;
+        if context.expect_continuation {
+            return Err(ErrorType::ExpectedContinuation);
+        }
+
+        let s = s.trim();
+        // The first character ('@') has been removed, thus the check is for the second character
+        let persistent = s.starts_with('@');
+        let s = if persistent { &s[1..] } else { s }; // Remove the '@' if it's present
+        let instruction_type = ConfigInstructionType::parse(s, persistent)?;
 
         Ok(Self {
             instruction_type,
@@ -124,9 +194,33 @@ impl InstructionTrait for ConfigInstruction {
                 }
                 ConfigInstructionType::Hidden(hidden) => config.hidden = *hidden,
                 ConfigInstructionType::Expect(expect) => config.expect = *expect,
-                ConfigInstructionType::Interval(interval) => config.interval = *interval,
+                ConfigInstructionType::Interval(interval) => config.interval = Some(*interval),
                 ConfigInstructionType::StartLag(delay) => config.start_lag = *delay,
                 ConfigInstructionType::EndLag(delay) => config.end_lag = *delay,
+                ConfigInstructionType::Jitter(jitter) => config.jitter = *jitter,
+                ConfigInstructionType::Pause(delay) => config.pause = *delay,
+                ConfigInstructionType::LinePause(delay) => config.line_pause = *delay,
+                ConfigInstructionType::PrintPace(pace) => config.print_pace = *pace,
+                ConfigInstructionType::Typos(typos, seed) => {
+                    config.typos = *typos;
+                    if let Some(seed) = seed {
+                        context.rng = util::Rng::new(*seed);
+                    }
+                }
+                ConfigInstructionType::Wpm(wpm) => config.wpm = *wpm,
+                ConfigInstructionType::Paste(paste, pause) => {
+                    config.paste = *paste;
+                    if let Some(pause) = pause {
+                        config.paste_pause = *pause;
+                    }
+                }
+                ConfigInstructionType::Highlight(highlight) => config.highlight = *highlight,
+                ConfigInstructionType::StatusIndicator(indicator) => {
+                    config.status_indicator.clone_from(indicator);
+                }
+                ConfigInstructionType::Set(name, value) => {
+                    context.variables.insert(name.clone(), value.clone());
+                }
             }
         } else {
             let config = &mut context.temporary;
@@ -146,9 +240,33 @@ impl InstructionTrait for ConfigInstruction {
                 }
                 ConfigInstructionType::Hidden(hidden) => config.hidden = Some(*hidden),
                 ConfigInstructionType::Expect(expect) => config.expect = Some(*expect),
-                ConfigInstructionType::Interval(interval) => config.interval = Some(*interval),
+                ConfigInstructionType::Interval(interval) => {
+                    config.interval = Some(Some(*interval));
+                }
                 ConfigInstructionType::StartLag(delay) => config.start_lag = Some(*delay),
                 ConfigInstructionType::EndLag(delay) => config.end_lag = Some(*delay),
+                ConfigInstructionType::Jitter(jitter) => config.jitter = Some(*jitter),
+                ConfigInstructionType::Pause(delay) => config.pause = Some(*delay),
+                ConfigInstructionType::LinePause(delay) => config.line_pause = Some(*delay),
+                ConfigInstructionType::PrintPace(pace) => config.print_pace = Some(*pace),
+                ConfigInstructionType::Typos(typos, seed) => {
+                    config.typos = Some(*typos);
+                    if let Some(seed) = seed {
+                        config.seed = Some(Some(*seed));
+                    }
+                }
+                ConfigInstructionType::Wpm(wpm) => config.wpm = Some(*wpm),
+                ConfigInstructionType::Paste(paste, pause) => {
+                    config.paste = Some(*paste);
+                    if let Some(pause) = pause {
+                        config.paste_pause = Some(*pause);
+                    }
+                }
+                ConfigInstructionType::Highlight(highlight) => config.highlight = Some(*highlight),
+                ConfigInstructionType::StatusIndicator(indicator) => {
+                    config.status_indicator = Some(indicator.clone());
+                }
+                ConfigInstructionType::Set(..) => unreachable!("Should be rejected when parsing"),
             }
         }
 
@@ -182,6 +300,34 @@ mod tests {
             ("@interval 2ms", Interval(2_000)),
             ("@start-lag 1s", StartLag(1_000_000)),
             ("@end-lag 1s", EndLag(1_000_000)),
+            ("@jitter 30%", Jitter(30)),
+            ("@pause 150ms", Pause(150_000)),
+            ("line-pause 1s", LinePause(1_000_000)),
+            ("@print-pace line 150ms", PrintPace(Pace::Line(150_000))),
+            ("print-pace word 40ms", PrintPace(Pace::Word(40_000))),
+            ("print-pace char", PrintPace(Pace::Character)),
+            ("@typos 5%", Typos(5, None)),
+            ("typos 5% 42", Typos(5, Some(42))),
+            ("@wpm 70", Wpm(70)),
+            ("@paste", Paste(Some(false), None)),
+            ("paste false", Paste(None, None)),
+            ("paste bracketed 500ms", Paste(Some(true), Some(500_000))),
+            ("@highlight", Highlight(true)),
+            ("highlight false", Highlight(false)),
+            (
+                "@status-indicator \"✘ {status}\"",
+                StatusIndicator("✘ {status}".to_string()),
+            ),
+            ("status-indicator", StatusIndicator(String::new())),
+            (
+                "@set NAME castwright",
+                Set("NAME".to_string(), "castwright".to_string()),
+            ),
+            (
+                "@set _VERSION \"0.1.0 \"",
+                Set("_VERSION".to_string(), "0.1.0 ".to_string()),
+            ),
+            ("@set EMPTY", Set("EMPTY".to_string(), String::new())),
         ];
         for (line, expected) in &instructions {
             assert_eq!(
@@ -223,6 +369,30 @@ mod tests {
             "interval 2",
             "start-lag",
             "start-lag 1",
+            "jitter",
+            "jitter 30",
+            "jitter 101%",
+            "pause 1",
+            "print-pace",
+            "print-pace line",
+            "print-pace word fast",
+            "print-pace char 1s",
+            "print-pace page 1s",
+            "typos",
+            "typos 5",
+            "typos 5% -1",
+            "typos 5% 42 43",
+            "wpm",
+            "wpm 0",
+            "wpm fast",
+            "paste what",
+            "paste true 1",
+            "paste true 1s 2s",
+            "highlight what",
+            "set NAME value",
+            "@set",
+            "@set 1NAME value",
+            "@set NA-ME value",
         ];
         for line in &malformed_instructions {
             let parsed = ConfigInstruction::parse(line, &mut context).unwrap_err();
@@ -280,6 +450,99 @@ mod tests {
         assert_eq!(resolved.secondary_prompt, "| ".to_string());
         assert_eq!(resolved.line_continuation, "\\".to_string());
         assert!(resolved.hidden);
-        assert_eq!(resolved.interval, 2_000);
+        assert_eq!(resolved.typing_interval(), 2_000);
+    }
+
+    #[test]
+    fn interval_takes_precedence_over_wpm() {
+        let mut parse_context = ParseContext::new();
+        let mut context = ExecutionContext::new();
+        let sink = &mut std::io::sink(); // Drop all output
+        let mut cast = AsciiCast::new(sink);
+        let mut execute = |line: &str, context: &mut ExecutionContext| {
+            ConfigInstruction::parse(line, &mut parse_context)
+                .unwrap()
+                .execute(context, &mut cast)
+                .unwrap();
+        };
+
+        execute("@wpm 60", &mut context);
+        assert!(context.persistent.typing_model());
+        assert_eq!(context.persistent.typing_interval(), 200_000);
+
+        // A temporary interval takes precedence over the persistent wpm
+        execute("interval 50ms", &mut context);
+        let resolved = context.persistent.combine(context.temporary.get(true));
+        assert!(!resolved.typing_model());
+        assert_eq!(resolved.typing_interval(), 50_000);
+
+        // A persistent interval takes precedence, even over a later wpm
+        execute("@interval 80ms", &mut context);
+        execute("@wpm 70", &mut context);
+        assert!(!context.persistent.typing_model());
+        assert_eq!(context.persistent.typing_interval(), 80_000);
+
+        // So does it over a temporary wpm
+        execute("wpm 90", &mut context);
+        let resolved = context.persistent.combine(context.temporary.get(true));
+        assert!(!resolved.typing_model());
+        assert_eq!(resolved.typing_interval(), 80_000);
+    }
+
+    #[test]
+    fn temporary_seed() {
+        let mut parse_context = ParseContext::new();
+        let mut context = ExecutionContext::new();
+        let sink = &mut std::io::sink(); // Drop all output
+        let mut cast = AsciiCast::new(sink);
+        let mut execute = |line: &str, context: &mut ExecutionContext| {
+            ConfigInstruction::parse(line, &mut parse_context)
+                .unwrap()
+                .execute(context, &mut cast)
+                .unwrap();
+        };
+        let mut fresh = util::Rng::new(0);
+
+        // A temporary seed is scoped to the next instruction, leaving the shared generator as it is
+        execute("typos 5% 42", &mut context);
+        let resolved = context.persistent.combine(context.temporary.get(true));
+        assert_eq!(resolved.seed, Some(42));
+        assert_eq!(context.rng.next_offset(30), fresh.next_offset(30));
+        assert_eq!(context.persistent.seed, None);
+
+        // A persistent seed reseeds the shared generator
+        execute("@typos 5% 42", &mut context);
+        let mut seeded = util::Rng::new(42);
+        assert_eq!(context.rng.next_offset(30), seeded.next_offset(30));
+        assert_eq!(context.persistent.seed, None);
+    }
+
+    #[test]
+    fn execute_set_instruction() {
+        let mut parse_context = ParseContext::new();
+        let mut context = ExecutionContext::new();
+        let sink = &mut std::io::sink(); // Drop all output
+        let mut cast = AsciiCast::new(sink);
+        context
+            .injected
+            .insert("INJECTED".to_string(), "from outside".to_string());
+        let instructions = [
+            "@set NAME castwright",
+            "@set NAME \"CastWright\"",
+            "@set INJECTED from script",
+        ];
+
+        for line in &instructions {
+            ConfigInstruction::parse(line, &mut parse_context)
+                .unwrap()
+                .execute(&mut context, &mut cast)
+                .unwrap();
+        }
+
+        assert!(context.temporary.is_empty());
+        assert_eq!(
+            context.interpolate("${NAME}: ${INJECTED}"),
+            "CastWright: from outside"
+        );
     }
 }
@@ -1,7 +1,8 @@
 //! Module for front matter instructions.
 
 use super::{
-    AsciiCast, ErrorType, ExecutionContext, FrontMatterState, InstructionTrait, ParseContext, util,
+    AsciiCast, ErrorType, ExecutionContext, FrontMatterState, InstructionTrait, ParseContext,
+    TermTheme, Theme, util,
 };
 use serde_json::de::from_str;
 use std::time::Duration;
@@ -25,6 +26,18 @@ pub enum FrontMatterInstruction {
     Idle(Duration),
     // Captured environment variables.
     Capture(Vec<String>),
+    /// Seed for pseudo-random typing.
+    Seed(u64),
+    /// Whether to record simulated keystrokes as input events.
+    RecordInput(bool),
+    /// Theme for syntax highlighting.
+    Theme(Theme),
+    /// Whether to enable inline style markup for print instructions.
+    Markup(bool),
+    /// Tags of the asciicast.
+    Tags(Vec<String>),
+    /// Color theme of the recorded terminal.
+    TerminalTheme(TermTheme),
 }
 
 impl InstructionTrait for FrontMatterInstruction {
@@ -78,6 +91,37 @@ impl InstructionTrait for FrontMatterInstruction {
                     let env_vars: Vec<String> = from_str(value)?;
                     Ok(Self::Capture(env_vars))
                 }
+                "seed" => {
+                    let seed = value.parse()?;
+                    Ok(Self::Seed(seed))
+                }
+                "record-input" => match value {
+                    "true" => Ok(Self::RecordInput(true)),
+                    "false" => Ok(Self::RecordInput(false)),
+                    _ => Err(ErrorType::MalformedInstruction),
+                },
+                "theme" => Ok(Self::Theme(Theme::parse(value)?)),
+                "tags" => {
+                    let tags: Vec<String> = from_str(value)?;
+                    Ok(Self::Tags(tags))
+                }
+                "terminal-theme" => {
+                    let theme = TermTheme::parse(value).map_err(|e| match e {
+                        ErrorType::MalformedHeader => ErrorType::MalformedInstruction,
+                        e => e,
+                    })?;
+                    Ok(Self::TerminalTheme(theme))
+                }
+                "markup" => {
+                    // Markup is translated when parsing, so it takes effect right away
+                    let markup = match value {
+                        "true" => true,
+                        "false" => false,
+                        _ => return Err(ErrorType::MalformedInstruction),
+                    };
+                    context.markup = markup;
+                    Ok(Self::Markup(markup))
+                }
                 _ => Err(ErrorType::UnknownFrontMatter),
             }
         } else {
@@ -113,6 +157,22 @@ impl InstructionTrait for FrontMatterInstruction {
             Self::Capture(env_vars) => {
                 cast.capture(util::capture_env_vars(env_vars.clone()))?;
             }
+            Self::Seed(seed) => {
+                context.rng = util::Rng::new(*seed);
+            }
+            Self::RecordInput(record_input) => {
+                // Enabled from outside (e.g. the CLI) takes precedence
+                context.record_input |= *record_input;
+            }
+            Self::Theme(theme) => {
+                context.theme.clone_from(theme);
+            }
+            Self::Tags(tags) => {
+                cast.tags(tags.clone())?;
+            }
+            Self::TerminalTheme(theme) => {
+                cast.theme(theme.clone())?;
+            }
             _ => {}
         }
         Ok(())
@@ -161,6 +221,27 @@ mod tests {
                 "capture: [\"SHELL\", \"TERM\"]",
                 Capture(vec!["SHELL".to_string(), "TERM".to_string()]),
             ),
+            ("seed: 42", Seed(42)),
+            ("record-input: true", RecordInput(true)),
+            ("record-input: false", RecordInput(false)),
+            ("markup: true", Markup(true)),
+            (
+                "tags: [\"demo\", \"rust\"]",
+                Tags(vec!["demo".to_string(), "rust".to_string()]),
+            ),
+            (
+                r##"terminal-theme: {"fg": "#ffffff", "bg": "#000000", "palette": "#000000:#800000:#008000:#808000:#000080:#800080:#008080:#c0c0c0"}"##,
+                TerminalTheme(TermTheme {
+                    fg: "#ffffff".to_string(),
+                    bg: "#000000".to_string(),
+                    palette: [
+                        "#000000", "#800000", "#008000", "#808000", "#000080", "#800080",
+                        "#008080", "#c0c0c0",
+                    ]
+                    .map(ToString::to_string)
+                    .to_vec(),
+                }),
+            ),
         ];
         for (line, expected) in &instructions {
             assert_eq!(
@@ -199,6 +280,12 @@ mod tests {
             "idle: 1",
             "idle: 1.0",
             "shell: []", // Empty shell.
+            "seed: -1",
+            "seed: what",
+            "record-input: yes",
+            "theme: {\"unknown\": \"1\"}",
+            "theme: {\"command\": \"red\"}",
+            r##"terminal-theme: {"fg": "white", "bg": "#000000", "palette": "#000000"}"##,
         ];
         for line in &instructions {
             let parsed = FrontMatterInstruction::parse(line, &mut parse_context).unwrap_err();
@@ -0,0 +1,295 @@
+//! Module for shell syntax highlighting of typed commands.
+
+use super::{
+    ErrorType,
+    typing::{MISTAKE_PREFIX, Segment, segments},
+};
+use std::collections::HashMap;
+
+/// Kind of a highlighted token.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Kind {
+    /// Name of the command, e.g. `git`.
+    Command,
+    /// A flag, e.g. `-v` or `--verbose`.
+    Flag,
+    /// A quoted string.
+    String,
+    /// A variable, e.g. `$HOME` or `${HOME}`.
+    Variable,
+    /// An operator, e.g. `|`, `&&` or `>`.
+    Operator,
+    /// A comment, starting with `#`.
+    Comment,
+}
+
+/// Colours for each kind of token, as SGR parameters (e.g. `1;32` for bold green). An empty string means no colour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Colour of command names.
+    command: String,
+    /// Colour of flags.
+    flag: String,
+    /// Colour of quoted strings.
+    string: String,
+    /// Colour of variables.
+    variable: String,
+    /// Colour of operators.
+    operator: String,
+    /// Colour of comments.
+    comment: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            command: "32".to_string(),
+            flag: "36".to_string(),
+            string: "33".to_string(),
+            variable: "35".to_string(),
+            operator: "1".to_string(),
+            comment: "90".to_string(),
+        }
+    }
+}
+
+impl Theme {
+    /// Parse a theme from a JSON object, mapping token kinds to SGR parameters. Kinds not given keep their default colours.
+    pub fn parse(s: &str) -> Result<Self, ErrorType> {
+        let colours: HashMap<String, String> = serde_json::from_str(s)?;
+        let mut theme = Self::default();
+        for (kind, colour) in colours {
+            if !colour.chars().all(|c| c.is_ascii_digit() || c == ';') {
+                return Err(ErrorType::MalformedInstruction);
+            }
+            let field = match kind.as_str() {
+                "command" => &mut theme.command,
+                "flag" => &mut theme.flag,
+                "string" => &mut theme.string,
+                "variable" => &mut theme.variable,
+                "operator" => &mut theme.operator,
+                "comment" => &mut theme.comment,
+                _ => return Err(ErrorType::MalformedInstruction),
+            };
+            *field = colour;
+        }
+        Ok(theme)
+    }
+    /// Get the colour of given kind of token.
+    fn colour(&self, kind: Kind) -> &str {
+        match kind {
+            Kind::Command => &self.command,
+            Kind::Flag => &self.flag,
+            Kind::String => &self.string,
+            Kind::Variable => &self.variable,
+            Kind::Operator => &self.operator,
+            Kind::Comment => &self.comment,
+        }
+    }
+}
+
+/// Highlight given command (possibly containing deliberate mistakes) by inserting SGR sequences, which are output without typing delay. Deliberate mistakes take the colour of where they are typed.
+pub fn highlight(command: &str, theme: &Theme) -> String {
+    // Tokenize the corrected command, then map its colours back to the segments
+    let segments = segments(command);
+    let corrected: String = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Text(text) => Some(*text),
+            Segment::Mistake(_) => None,
+        })
+        .collect();
+    let kinds = tokenize(&corrected);
+
+    let mut highlighted = String::with_capacity(command.len() * 2);
+    let mut current = "";
+    let mut offset = 0;
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => {
+                for (index, character) in text.char_indices() {
+                    let colour = kinds[offset + index].map_or("", |kind| theme.colour(kind));
+                    if colour != current {
+                        if !current.is_empty() {
+                            highlighted.push_str(RESET);
+                        }
+                        if !colour.is_empty() {
+                            highlighted.push_str("\x1b[");
+                            highlighted.push_str(colour);
+                            highlighted.push('m');
+                        }
+                        current = colour;
+                    }
+                    if index == 0 && text == MISTAKE_PREFIX {
+                        // Keep the escape of a literal `~{`
+                        highlighted.push('~');
+                    }
+                    highlighted.push(character);
+                }
+                offset += text.len();
+            }
+            Segment::Mistake(mistake) => {
+                highlighted.push_str("~{");
+                highlighted.push_str(mistake);
+                highlighted.push('}');
+            }
+        }
+    }
+    if !current.is_empty() {
+        highlighted.push_str(RESET);
+    }
+    highlighted
+}
+
+/// Sequence to reset all attributes.
+const RESET: &str = "\x1b[0m";
+/// Operators, longest first.
+const OPERATORS: [&str; 11] = ["&&", "||", ">>", ">&", "|", "&", ";", ">", "<", "(", ")"];
+/// Redirection operators, which are not followed by a command.
+const REDIRECTIONS: [&str; 4] = [">>", ">&", ">", "<"];
+
+/// Tokenize given command, returning the kind of token for each byte.
+fn tokenize(command: &str) -> Vec<Option<Kind>> {
+    let mut kinds = vec![None; command.len()];
+    let mut command_position = true;
+    let mut index = 0;
+    while let Some(character) = command[index..].chars().next() {
+        let rest = &command[index..];
+        let (len, kind) = if character.is_whitespace() {
+            (character.len_utf8(), None)
+        } else if character == '#' {
+            (rest.len(), Some(Kind::Comment))
+        } else if character == '\'' || character == '"' {
+            (quoted_len(rest, character), Some(Kind::String))
+        } else if character == '$' {
+            (variable_len(rest), Some(Kind::Variable))
+        } else if let Some(operator) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            command_position = !REDIRECTIONS.contains(operator);
+            (operator.len(), Some(Kind::Operator))
+        } else {
+            let len = word_len(rest);
+            let kind = if command_position {
+                command_position = false;
+                Kind::Command
+            } else if character == '-' {
+                Kind::Flag
+            } else {
+                index += len;
+                continue;
+            };
+            (len, Some(kind))
+        };
+        kinds[index..index + len].fill(kind);
+        index += len;
+    }
+    kinds
+}
+
+/// Length of a quoted string starting with given quote, including the closing quote if any.
+fn quoted_len(s: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (index, character) in s.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if character == '\\' && quote == '"' {
+            escaped = true;
+        } else if character == quote {
+            return index + 1;
+        }
+    }
+    s.len()
+}
+
+/// Length of a variable starting with `$`, e.g. `$HOME`, `${HOME}` or `$?`.
+fn variable_len(s: &str) -> usize {
+    let rest = &s[1..];
+    if rest.starts_with('{') {
+        return rest.find('}').map_or(s.len(), |end| end + 2);
+    }
+    let name = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    if name > 0 {
+        1 + name
+    } else {
+        // Special parameters like `$?`, or a lone `$`
+        1 + rest
+            .chars()
+            .next()
+            .filter(|c| "?!#$@*-".contains(*c))
+            .map_or(0, char::len_utf8)
+    }
+}
+
+/// Length of a plain word, ending at whitespace, quotes, variables or operators.
+fn word_len(s: &str) -> usize {
+    let mut escaped = false;
+    for (index, character) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if character == '\\' {
+            escaped = true;
+        } else if index > 0 && (character.is_whitespace() || "'\"$&|;<>()".contains(character)) {
+            return index;
+        }
+    }
+    s.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Highlight with a theme using single-letter markers, for readability.
+    fn marked(command: &str) -> String {
+        let theme = Theme::default();
+        highlight(command, &theme)
+            .replace(RESET, "]")
+            .replace(&format!("\x1b[{}m", theme.command), "C[")
+            .replace(&format!("\x1b[{}m", theme.flag), "F[")
+            .replace(&format!("\x1b[{}m", theme.string), "S[")
+            .replace(&format!("\x1b[{}m", theme.variable), "V[")
+            .replace(&format!("\x1b[{}m", theme.operator), "O[")
+            .replace(&format!("\x1b[{}m", theme.comment), "#[")
+    }
+
+    #[test]
+    fn tokens() {
+        let cases = [
+            ("ls", "C[ls]"),
+            ("ls -la ~", "C[ls] F[-la] ~"),
+            (
+                "echo \"Hi $USER\" | grep -i hi",
+                "C[echo] S[\"Hi $USER\"] O[|] C[grep] F[-i] hi",
+            ),
+            ("cd $HOME && ls", "C[cd] V[$HOME] O[&&] C[ls]"),
+            ("cat < in > out 2>&1", "C[cat] O[<] in O[>] out 2O[>&]1"),
+            ("echo ${A}b # note", "C[echo] V[${A}]b #[# note]"),
+            ("echo 'a\\' $?", "C[echo] S['a\\'] V[$?]"),
+        ];
+        for (command, expected) in cases {
+            assert_eq!(marked(command), expected, "Command: {command}");
+        }
+    }
+
+    #[test]
+    fn mistakes() {
+        assert_eq!(marked("git ~{comit}commit"), "C[git] ~{comit}commit");
+        assert_eq!(marked("~{sl}ls"), "~{sl}C[ls]");
+        assert_eq!(marked("gi~{y}t status"), "C[gi~{y}t] status");
+        assert_eq!(marked("echo ~~{x}"), "C[echo] ~~{x}");
+    }
+
+    #[test]
+    fn theme() {
+        let theme = Theme::parse(r#"{"command": "1;31", "comment": ""}"#).unwrap();
+        assert_eq!(theme.command, "1;31");
+        assert_eq!(theme.comment, "");
+        assert_eq!(theme.flag, Theme::default().flag);
+        assert_eq!(highlight("ls # hi", &theme), "\x1b[1;31mls\x1b[0m # hi");
+
+        for malformed in [r#"{"unknown": "1"}"#, r#"{"command": "red"}"#, "[]"] {
+            assert!(Theme::parse(malformed).is_err(), "Theme: {malformed}");
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Module for include instructions.
+
+use super::{
+    AsciiCast, ErrorType, ExecutionContext, FrontMatterState, InstructionTrait, ParseContext, util,
+};
+use std::path::{Path, PathBuf};
+
+/// An include instruction, which splices another script in place at parse time.
+#[derive(Debug, PartialEq, Eq)]
+pub struct IncludeInstruction(PathBuf);
+
+impl IncludeInstruction {
+    /// Check if a trimmed line (without the leading `@`) is an include instruction.
+    pub fn matches(s: &str) -> bool {
+        s.strip_prefix("include")
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+    }
+    /// Resolve the included path relative to given directory, returning its canonical form.
+    pub fn resolve(&self, directory: &Path) -> Result<PathBuf, ErrorType> {
+        Ok(directory.join(&self.0).canonicalize()?)
+    }
+}
+
+impl InstructionTrait for IncludeInstruction {
+    /// Parse a trimmed line (without the leading `@`) into an `IncludeInstruction`.
+    fn parse(s: &str, context: &mut ParseContext) -> Result<Self, ErrorType> {
+        // Do not end the front matter, since the included script might contain one
+        if context.front_matter_state == FrontMatterState::Start {
+            return Err(ErrorType::ExpectedKeyValuePair);
+        }
+        if context.expect_continuation {
+            return Err(ErrorType::ExpectedContinuation);
+        }
+
+        let path = util::parse_loose_string(s["include".len()..].trim())?;
+        if path.is_empty() {
+            return Err(ErrorType::MalformedInstruction);
+        }
+
+        Ok(Self(PathBuf::from(path)))
+    }
+    /// Execute the instruction. Does nothing, since the included script is spliced by the caller.
+    fn execute(
+        &self,
+        _context: &mut ExecutionContext,
+        _cast: &mut AsciiCast<impl std::io::Write>,
+    ) -> Result<(), ErrorType> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn include_instruction() {
+        let instructions = [
+            ("include prelude.cwrt", "prelude.cwrt"),
+            ("include \"with space.cwrt\"", "with space.cwrt"),
+            ("include ../shared/prelude.cwrt", "../shared/prelude.cwrt"),
+        ];
+        for (input, expected) in &instructions {
+            assert!(IncludeInstruction::matches(input));
+            let mut context = ParseContext::new();
+            let instruction = IncludeInstruction::parse(input, &mut context).unwrap();
+            assert_eq!(instruction.0, PathBuf::from(expected));
+            assert_eq!(context.front_matter_state, FrontMatterState::None);
+        }
+    }
+
+    #[test]
+    fn not_include_instruction() {
+        let instructions = ["interval 1s", "includes file", "@include file"];
+        for input in &instructions {
+            assert!(!IncludeInstruction::matches(input), "`{input}`");
+        }
+    }
+
+    #[test]
+    fn malformed_include_instruction() {
+        let mut context = ParseContext::new();
+        let err = IncludeInstruction::parse("include", &mut context).unwrap_err();
+        assert!(
+            matches!(err, ErrorType::MalformedInstruction),
+            "Expected MalformedInstruction, got {err:?}"
+        );
+
+        context.expect_continuation = true;
+        let err = IncludeInstruction::parse("include file", &mut context).unwrap_err();
+        assert!(
+            matches!(err, ErrorType::ExpectedContinuation),
+            "Expected ExpectedContinuation, got {err:?}"
+        );
+
+        let mut context = ParseContext::new();
+        context.front_matter_state.next().unwrap();
+        let err = IncludeInstruction::parse("include file", &mut context).unwrap_err();
+        assert!(
+            matches!(err, ErrorType::ExpectedKeyValuePair),
+            "Expected ExpectedKeyValuePair, got {err:?}"
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Module for macro instructions.
+
+use super::{
+    AsciiCast, ErrorType, ExecutionContext, Instruction, InstructionTrait, ParseContext, util,
+};
+
+/// A macro instruction, which defines or calls a named block of instructions.
+#[derive(Debug)]
+pub enum MacroInstruction {
+    /// Start of a macro definition.
+    Define,
+    /// A line recorded into the macro being defined.
+    Record,
+    /// End of a macro definition.
+    End,
+    /// A macro call, expanded into the instructions of the macro body.
+    Call(Vec<Instruction>),
+}
+
+impl MacroInstruction {
+    /// Check if a trimmed line (without the leading `@`) is a macro instruction.
+    pub fn matches(s: &str) -> bool {
+        matches!(s.split_whitespace().next(), Some("macro" | "call"))
+    }
+    /// Record a line into the macro being defined, or end the definition if the line is `@end`. Lines are trimmed, except those inside a print block, which are recorded as they are.
+    pub fn record(s: &str, context: &mut ParseContext) -> Result<Self, ErrorType> {
+        let Some((name, lines)) = context.recording.as_mut() else {
+            unreachable!("Should only be called when recording");
+        };
+        if let Some(delimiter) = &context.recording_block {
+            // Lines in a print block are recorded untrimmed and never end the definition
+            if s.trim() == delimiter {
+                context.recording_block = None;
+            }
+            lines.push(s.to_string());
+            return Ok(Self::Record);
+        }
+        let s = s.trim();
+        if let Some(delimiter) = s
+            .strip_prefix('%')
+            .and_then(|rest| rest.trim().strip_prefix("<<"))
+        {
+            // Malformed delimiters are left to be reported when the macro is called
+            let delimiter = delimiter.trim();
+            if !delimiter.is_empty() && !delimiter.contains(char::is_whitespace) {
+                context.recording_block = Some(delimiter.to_string());
+            }
+        }
+        let Some(rest) = s.strip_prefix('@').map(str::trim) else {
+            lines.push(s.to_string());
+            return Ok(Self::Record);
+        };
+        match rest.split_whitespace().next() {
+            Some("end") if context.recording_depth == 0 => {
+                let name = std::mem::take(name);
+                let lines = std::mem::take(lines);
+                context.recording = None;
+                context.macros.insert(name, lines);
+                return Ok(Self::End);
+            }
+            // Nested definitions are not allowed
+            Some("macro") => return Err(ErrorType::ExpectedEnd),
+            // Keep track of conditional blocks, which are also ended by `@end`
+            Some("if") => context.recording_depth += 1,
+            Some("end") => context.recording_depth -= 1,
+            _ => {}
+        }
+        lines.push(s.to_string());
+        Ok(Self::Record)
+    }
+}
+
+impl InstructionTrait for MacroInstruction {
+    /// Parse a trimmed line (without the leading `@`) into a `MacroInstruction`.
+    fn parse(s: &str, context: &mut ParseContext) -> Result<Self, ErrorType> {
+        context.front_matter_state.end()?;
+        if context.expect_continuation {
+            return Err(ErrorType::ExpectedContinuation);
+        }
+
+        let (keyword, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let rest = rest.trim();
+        match keyword {
+            "macro" => {
+                if rest.is_empty() || rest.contains(char::is_whitespace) {
+                    return Err(ErrorType::MalformedInstruction);
+                }
+                context.recording = Some((rest.to_string(), Vec::new()));
+                context.recording_depth = 0;
+                context.recording_block = None;
+                Ok(Self::Define)
+            }
+            "call" => {
+                let (name, arguments) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if name.is_empty() {
+                    return Err(ErrorType::MalformedInstruction);
+                }
+                let arguments = util::parse_arguments(arguments)?;
+                let body = context
+                    .macros
+                    .get(name)
+                    .ok_or(ErrorType::UnknownMacro)?
+                    .clone();
+                if context.expanding.iter().any(|expanding| expanding == name) {
+                    return Err(ErrorType::RecursiveMacro);
+                }
+
+                // Conditional blocks must be balanced within the macro body
+                let conditionals = std::mem::take(&mut context.conditionals);
+                context.expanding.push(name.to_string());
+                let instructions = body
+                    .iter()
+                    .map(|line| {
+                        let line = util::substitute_arguments(line, &arguments);
+                        match Instruction::parse(&line, context)? {
+                            // Includes are spliced by the caller, which does not see expanded instructions
+                            Instruction::Include(_) => Err(ErrorType::MalformedInstruction),
+                            instruction => Ok(instruction),
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>();
+                context.expanding.pop();
+                let balanced = context.conditionals.is_empty();
+                context.conditionals = conditionals;
+
+                let instructions = instructions?;
+                if balanced {
+                    Ok(Self::Call(instructions))
+                } else {
+                    Err(ErrorType::ExpectedEnd)
+                }
+            }
+            _ => Err(ErrorType::UnknownInstruction),
+        }
+    }
+    /// Execute the instruction. Only macro calls have an effect, which executes the expanded instructions in order.
+    fn execute(
+        &self,
+        context: &mut ExecutionContext,
+        cast: &mut AsciiCast<impl std::io::Write>,
+    ) -> Result<(), ErrorType> {
+        if let Self::Call(instructions) = self {
+            for instruction in instructions {
+                instruction.execute(context, cast)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse given lines with the same context, returning the last instruction.
+    fn parse_lines(lines: &[&str], context: &mut ParseContext) -> Result<Instruction, ErrorType> {
+        let mut last = Instruction::parse("", context)?;
+        for line in lines {
+            last = Instruction::parse(line, context)?;
+        }
+        Ok(last)
+    }
+
+    #[test]
+    fn define_macro() {
+        let mut context = ParseContext::new();
+        let lines = ["@macro show", "% Showing ${1}", "$ cat ${1}", "@end"];
+        parse_lines(&lines, &mut context).unwrap();
+        assert!(context.recording.is_none());
+        assert_eq!(
+            context.macros.get("show").unwrap(),
+            &vec!["% Showing ${1}".to_string(), "$ cat ${1}".to_string()]
+        );
+    }
+
+    #[test]
+    fn call_macro() {
+        let mut context = ParseContext::new();
+        let lines = [
+            "@macro show",
+            "% Showing ${1}",
+            "$ cat ${1} \\",
+            "> ${2}",
+            "@end",
+            "@call show \"file name\" | head",
+        ];
+        let Instruction::Macro(MacroInstruction::Call(instructions)) =
+            parse_lines(&lines, &mut context).unwrap()
+        else {
+            panic!("Expected a macro call");
+        };
+        let mut expected_context = ParseContext::new();
+        let expected = [
+            Instruction::parse("% Showing file name", &mut expected_context).unwrap(),
+            Instruction::parse("$ cat file name \\", &mut expected_context).unwrap(),
+            Instruction::parse("> |", &mut expected_context).unwrap(),
+        ];
+        assert_eq!(instructions, expected);
+        assert!(context.expanding.is_empty());
+    }
+
+    #[test]
+    fn nested_macro_call() {
+        let mut context = ParseContext::new();
+        let lines = [
+            "@macro inner",
+            "% Inner ${1}",
+            "@end",
+            "@macro outer",
+            "@call inner ${1}",
+            "@end",
+            "@call outer argument",
+        ];
+        let Instruction::Macro(MacroInstruction::Call(instructions)) =
+            parse_lines(&lines, &mut context).unwrap()
+        else {
+            panic!("Expected a macro call");
+        };
+        let Instruction::Macro(MacroInstruction::Call(inner)) = &instructions[0] else {
+            panic!("Expected a nested macro call");
+        };
+        let expected = Instruction::parse("% Inner argument", &mut ParseContext::new()).unwrap();
+        assert_eq!(inner, &[expected]);
+    }
+
+    #[test]
+    fn macro_errors() {
+        let cases: [(&[&str], ErrorType); 7] = [
+            (&["@call undefined"], ErrorType::UnknownMacro),
+            (&["@end"], ErrorType::UnexpectedEnd),
+            (&["@macro"], ErrorType::MalformedInstruction),
+            (&["@macro two words"], ErrorType::MalformedInstruction),
+            (&["@macro outer", "@macro inner"], ErrorType::ExpectedEnd),
+            (
+                &["@macro loop", "@call loop", "@end", "@call loop"],
+                ErrorType::RecursiveMacro,
+            ),
+            (
+                &["@macro include", "@include file", "@end", "@call include"],
+                ErrorType::MalformedInstruction,
+            ),
+        ];
+        for (lines, expected) in cases {
+            let mut context = ParseContext::new();
+            let err = parse_lines(lines, &mut context).unwrap_err();
+            assert_eq!(err, expected, "Lines: {lines:?}");
+        }
+    }
+
+    #[test]
+    fn print_block_in_macro() {
+        let mut context = ParseContext::new();
+        let lines = [
+            "@macro banner",
+            "  % <<EOF",
+            "  Indented ${1}",
+            "@end",
+            "EOF",
+            "@end",
+            "@call banner line",
+        ];
+        let Instruction::Macro(MacroInstruction::Call(instructions)) =
+            parse_lines(&lines, &mut context).unwrap()
+        else {
+            panic!("Expected a macro call");
+        };
+        assert_eq!(
+            context.macros.get("banner").unwrap(),
+            &vec![
+                "% <<EOF".to_string(),
+                "  Indented ${1}".to_string(),
+                "@end".to_string(),
+                "EOF".to_string(),
+            ]
+        );
+        let mut expected_context = ParseContext::new();
+        let expected = ["% <<EOF", "  Indented line", "@end", "EOF"]
+            .iter()
+            .map(|line| Instruction::parse(line, &mut expected_context).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(instructions, expected);
+        assert!(context.print_block.is_none());
+    }
+
+    #[test]
+    fn conditional_in_macro() {
+        let mut context = ParseContext::new();
+        let lines = [
+            "@macro maybe",
+            "@if env ${1}",
+            "% Set",
+            "@end",
+            "@end",
+            "@call maybe HOME",
+        ];
+        let Instruction::Macro(MacroInstruction::Call(instructions)) =
+            parse_lines(&lines, &mut context).unwrap()
+        else {
+            panic!("Expected a macro call");
+        };
+        assert_eq!(instructions.len(), 3);
+        assert!(context.conditionals.is_empty());
+
+        let lines = [
+            "@macro unbalanced",
+            "@else",
+            "@end",
+            "@if env HOME",
+            "@call unbalanced",
+        ];
+        let err = parse_lines(&lines, &mut ParseContext::new()).unwrap_err();
+        assert_eq!(err, ErrorType::UnexpectedElse);
+    }
+
+    #[test]
+    fn unterminated_macro() {
+        let mut context = ParseContext::new();
+        parse_lines(&["@macro unterminated", "% Hello"], &mut context).unwrap();
+        assert_eq!(context.finish(), Err(ErrorType::ExpectedEnd));
+    }
+}
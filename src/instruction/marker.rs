@@ -22,7 +22,11 @@ impl InstructionTrait for MarkerInstruction {
         context: &mut ExecutionContext,
         cast: &mut AsciiCast<impl std::io::Write>,
     ) -> Result<(), ErrorType> {
-        cast.marker(context.elapsed, &self.0)?;
+        let name = context.interpolate(&self.0);
+        if let Some(script) = &mut context.export {
+            script.marker(&name);
+        }
+        cast.marker(context.elapsed, &name)?;
 
         Ok(())
     }
@@ -0,0 +1,176 @@
+//! Module for inline style markup in print instructions.
+
+use super::ErrorType;
+
+/// Sequence to reset all attributes.
+const RESET: &str = "\x1b[0m";
+
+/// Translate inline style markup into SGR sequences. Supported syntax:
+///
+/// - `[style]`: Apply given style, consisting of whitespace-separated attributes and colours, e.g. `[bold red on white]`.
+/// - `[/]`: Close the most recent style, restoring the styles opened before it.
+/// - `[[`: A literal `[`.
+///
+/// Styles left open are closed at the end of the text.
+pub fn translate(text: &str) -> Result<String, ErrorType> {
+    let mut translated = String::with_capacity(text.len());
+    let mut styles: Vec<String> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        translated.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(after) = after.strip_prefix('[') {
+            translated.push('[');
+            rest = after;
+            continue;
+        }
+        let end = after.find(']').ok_or(ErrorType::MalformedInstruction)?;
+        let tag = &after[..end];
+        if tag == "/" {
+            styles.pop().ok_or(ErrorType::MalformedInstruction)?;
+            translated.push_str(RESET);
+            for style in &styles {
+                push_sgr(&mut translated, style);
+            }
+        } else {
+            let style = parse_style(tag)?;
+            push_sgr(&mut translated, &style);
+            styles.push(style);
+        }
+        rest = &after[end + 1..];
+    }
+    translated.push_str(rest);
+    if !styles.is_empty() {
+        translated.push_str(RESET);
+    }
+    Ok(translated)
+}
+
+/// Push an SGR sequence with given parameters.
+fn push_sgr(s: &mut String, parameters: &str) {
+    s.push_str("\x1b[");
+    s.push_str(parameters);
+    s.push('m');
+}
+
+/// Parse a style, e.g. `bold red on white`, into SGR parameters.
+fn parse_style(tag: &str) -> Result<String, ErrorType> {
+    let mut parameters = Vec::new();
+    let mut words = tag.split_whitespace();
+    while let Some(word) = words.next() {
+        let parameter = if word == "on" {
+            let colour = words.next().ok_or(ErrorType::MalformedInstruction)?;
+            parse_colour(colour, true)?
+        } else if let Some(attribute) = attribute(word) {
+            attribute.to_string()
+        } else {
+            parse_colour(word, false)?
+        };
+        parameters.push(parameter);
+    }
+    if parameters.is_empty() {
+        return Err(ErrorType::MalformedInstruction);
+    }
+    Ok(parameters.join(";"))
+}
+
+/// Get the SGR parameter of given text attribute.
+fn attribute(word: &str) -> Option<u8> {
+    let parameter = match word {
+        "bold" => 1,
+        "dim" => 2,
+        "italic" => 3,
+        "underline" => 4,
+        "blink" => 5,
+        "reverse" => 7,
+        "hidden" => 8,
+        "strike" => 9,
+        _ => return None,
+    };
+    Some(parameter)
+}
+
+/// Parse a colour, as a name (e.g. `red`, `bright_red`), a 256-colour index (e.g. `208`) or a truecolor hex value (e.g. `#ff8700`), into SGR parameters.
+fn parse_colour(word: &str, background: bool) -> Result<String, ErrorType> {
+    let (base, extended) = if background { (40, 48) } else { (30, 38) };
+    if let Some(hex) = word.strip_prefix('#') {
+        if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ErrorType::MalformedInstruction);
+        }
+        let channel = |range| {
+            u8::from_str_radix(&hex[range], 16).map_err(|_| ErrorType::MalformedInstruction)
+        };
+        let (r, g, b) = (channel(0..2)?, channel(2..4)?, channel(4..6)?);
+        return Ok(format!("{extended};2;{r};{g};{b}"));
+    }
+    if word.starts_with(|c: char| c.is_ascii_digit()) {
+        let index: u8 = word.parse()?;
+        return Ok(format!("{extended};5;{index}"));
+    }
+    let (bright, name) = word
+        .strip_prefix("bright_")
+        .map_or((false, word), |name| (true, name));
+    let offset = match name {
+        "black" => 0,
+        "red" => 1,
+        "green" => 2,
+        "yellow" => 3,
+        "blue" => 4,
+        "magenta" => 5,
+        "cyan" => 6,
+        "white" => 7,
+        "default" if !bright => 9,
+        _ => return Err(ErrorType::MalformedInstruction),
+    };
+    let base = if bright { base + 60 } else { base };
+    Ok((base + offset).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styles() {
+        let cases = [
+            ("plain", "plain"),
+            ("[bold red]Warning[/]", "\x1b[1;31mWarning\x1b[0m"),
+            ("[on blue]x[/]", "\x1b[44mx\x1b[0m"),
+            ("[bright_green on bright_black]x", "\x1b[92;100mx\x1b[0m"),
+            (
+                "[208]x[/] [#ff8700]y[/]",
+                "\x1b[38;5;208mx\x1b[0m \x1b[38;2;255;135;0my\x1b[0m",
+            ),
+            ("[on 17]x[/]", "\x1b[48;5;17mx\x1b[0m"),
+            ("[[not a tag] ok]", "[not a tag] ok]"),
+            (
+                "[bold]a [red]b[/] c[/]",
+                "\x1b[1ma \x1b[31mb\x1b[0m\x1b[1m c\x1b[0m",
+            ),
+        ];
+        for (markup, expected) in cases {
+            assert_eq!(translate(markup).unwrap(), expected, "Markup: {markup}");
+        }
+    }
+
+    #[test]
+    fn malformed() {
+        for markup in [
+            "[unknown]x",
+            "[bold",
+            "x[/]",
+            "[]",
+            "[on]",
+            "[256]",
+            "[#ff87]",
+            "[#+f+f+f]",
+            "[bright_default]",
+        ] {
+            assert_eq!(
+                translate(markup),
+                Err(ErrorType::MalformedInstruction),
+                "Markup: {markup}"
+            );
+        }
+    }
+}
@@ -1,22 +1,36 @@
 //! Module for parsing and executing instructions.
 
 mod command;
+mod conditional;
 mod config;
 mod empty;
 mod frontmatter;
+mod highlight;
+mod include;
+mod macros;
 mod marker;
+mod markup;
 mod print;
+mod prompt;
+mod screen;
+mod typing;
 mod wait;
 
 use super::{
-    AsciiCast, ErrorType, ExecutionContext, FrontMatterState, ParseContext, execute_command, util,
+    AsciiCast, Configuration, ErrorType, ExecutionContext, FrontMatterState, ParseContext,
+    TermTheme, execute_command, util,
 };
 pub use command::CommandInstruction;
+pub use conditional::ConditionalInstruction;
 pub use config::ConfigInstruction;
 pub use empty::EmptyInstruction;
 pub use frontmatter::FrontMatterInstruction;
+pub use highlight::Theme;
+pub use include::IncludeInstruction;
+pub use macros::MacroInstruction;
 pub use marker::MarkerInstruction;
-pub use print::PrintInstruction;
+pub use print::{Pace, PrintInstruction};
+pub use screen::ScreenInstruction;
 pub use wait::WaitInstruction;
 
 /// Trait for instructions.
@@ -57,11 +71,23 @@ pub enum Instruction {
     Command(CommandInstruction),
     Wait(WaitInstruction),
     FrontMatter(FrontMatterInstruction),
+    Include(IncludeInstruction),
+    Macro(MacroInstruction),
+    Conditional(ConditionalInstruction),
+    Screen(ScreenInstruction),
 }
 
 impl InstructionTrait for Instruction {
     /// Parse an instruction from a string.
     fn parse(s: &str, context: &mut ParseContext) -> Result<Self, ErrorType> {
+        if context.print_block.is_some() {
+            // Lines are recorded into the print block as they are, until the closing delimiter
+            return Ok(Self::Print(PrintInstruction::record(s, context)?));
+        }
+        if context.recording.is_some() {
+            // Lines are recorded into the macro being defined instead of parsed
+            return Ok(Self::Macro(MacroInstruction::record(s, context)?));
+        }
         let s = s.trim();
         let Some(first) = s.chars().next() else {
             return Ok(Self::Empty(EmptyInstruction::new()));
@@ -70,6 +96,18 @@ impl InstructionTrait for Instruction {
         context.start = first;
 
         match first {
+            '@' if IncludeInstruction::matches(&trimmed) => {
+                Ok(Self::Include(IncludeInstruction::parse(&trimmed, context)?))
+            }
+            '@' if ConditionalInstruction::matches(&trimmed) => Ok(Self::Conditional(
+                ConditionalInstruction::parse(&trimmed, context)?,
+            )),
+            '@' if MacroInstruction::matches(&trimmed) => {
+                Ok(Self::Macro(MacroInstruction::parse(&trimmed, context)?))
+            }
+            '@' if ScreenInstruction::matches(&trimmed) => {
+                Ok(Self::Screen(ScreenInstruction::parse(&trimmed, context)?))
+            }
             '@' => Ok(Self::Config(ConfigInstruction::parse(&trimmed, context)?)),
             '%' => Ok(Self::Print(PrintInstruction::parse(&trimmed, context)?)),
             '!' => Ok(Self::Marker(MarkerInstruction::parse(&trimmed, context)?)),
@@ -88,6 +126,10 @@ impl InstructionTrait for Instruction {
         context: &mut ExecutionContext,
         cast: &mut AsciiCast<impl std::io::Write>,
     ) -> Result<(), ErrorType> {
+        if context.skipping() && !matches!(self, Self::Conditional(_)) {
+            // Instructions in inactive branches produce no effects at all
+            return Ok(());
+        }
         match self {
             Self::Config(instruction) => instruction.execute(context, cast),
             Self::Print(instruction) => instruction.execute(context, cast),
@@ -96,6 +138,10 @@ impl InstructionTrait for Instruction {
             Self::Command(instruction) => instruction.execute(context, cast),
             Self::Wait(instruction) => instruction.execute(context, cast),
             Self::FrontMatter(instruction) => instruction.execute(context, cast),
+            Self::Include(instruction) => instruction.execute(context, cast),
+            Self::Macro(instruction) => instruction.execute(context, cast),
+            Self::Conditional(instruction) => instruction.execute(context, cast),
+            Self::Screen(instruction) => instruction.execute(context, cast),
         }
     }
 }
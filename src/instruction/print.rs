@@ -1,10 +1,72 @@
 //! Module for print instructions.
 
-use super::{AsciiCast, ErrorType, ExecutionContext, InstructionTrait, ParseContext, util};
+use super::{
+    AsciiCast, ErrorType, ExecutionContext, InstructionTrait, ParseContext, markup,
+    typing::type_text, util,
+};
+
+/// How the content of print instructions is revealed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Pace {
+    /// Typed character by character, like commands.
+    #[default]
+    Character,
+    /// Revealed word by word, with given delay in microseconds (µs) before each word.
+    Word(u128),
+    /// Revealed line by line, with given delay in microseconds (µs) before each line.
+    Line(u128),
+}
+
+impl Pace {
+    /// Parse a pace from words, e.g. `line 150ms`, `word 40ms` or `char`.
+    pub fn parse<'a>(mut words: impl Iterator<Item = &'a str>) -> Result<Self, ErrorType> {
+        let mode = words.next().ok_or(ErrorType::MalformedInstruction)?;
+        let mut delay = || {
+            let delay = words.next().ok_or(ErrorType::MalformedInstruction)?;
+            Ok::<_, ErrorType>(util::parse_duration(delay)?.as_micros())
+        };
+        let pace = match mode {
+            "char" => Self::Character,
+            "word" => Self::Word(delay()?),
+            "line" => Self::Line(delay()?),
+            _ => return Err(ErrorType::MalformedInstruction),
+        };
+        if words.next().is_some() {
+            return Err(ErrorType::MalformedInstruction);
+        }
+        Ok(pace)
+    }
+}
 
 /// A print instruction.
 #[derive(Debug, PartialEq, Eq)]
-pub struct PrintInstruction(String);
+pub enum PrintInstruction {
+    /// Print given lines, as a single line or a block.
+    Print(Vec<String>),
+    /// Start of a print block, or a line recorded into it.
+    Record,
+}
+
+impl PrintInstruction {
+    /// Record a line into the print block being recorded, or end the block if the line is its closing delimiter. Note that the line is not trimmed, so that indentation is preserved.
+    pub fn record(s: &str, context: &mut ParseContext) -> Result<Self, ErrorType> {
+        let Some((delimiter, lines)) = context.print_block.as_mut() else {
+            unreachable!("Should only be called when recording a print block");
+        };
+        if s.trim() == delimiter {
+            let lines = std::mem::take(lines);
+            context.print_block = None;
+            return Ok(Self::Print(lines));
+        }
+        let line = if context.markup {
+            markup::translate(s)?
+        } else {
+            s.to_string()
+        };
+        lines.push(line);
+        Ok(Self::Record)
+    }
+}
 
 impl InstructionTrait for PrintInstruction {
     /// Parse a trimmed line into an `PrintInstruction`.
@@ -14,9 +76,24 @@ impl InstructionTrait for PrintInstruction {
             return Err(ErrorType::ExpectedContinuation);
         }
 
+        if let Some(delimiter) = s.strip_prefix("<<") {
+            // Start of a print block, ended by a line with the delimiter
+            let delimiter = delimiter.trim();
+            if delimiter.is_empty() || delimiter.contains(char::is_whitespace) {
+                return Err(ErrorType::MalformedInstruction);
+            }
+            context.print_block = Some((delimiter.to_string(), Vec::new()));
+            return Ok(Self::Record);
+        }
+
         let text = util::parse_loose_string(s)?;
+        let text = if context.markup {
+            markup::translate(&text)?
+        } else {
+            text
+        };
 
-        Ok(Self(text))
+        Ok(Self::Print(vec![text]))
     }
     /// Execute the instruction
     fn execute(
@@ -24,21 +101,52 @@ impl InstructionTrait for PrintInstruction {
         context: &mut ExecutionContext,
         cast: &mut AsciiCast<impl std::io::Write>,
     ) -> Result<(), ErrorType> {
+        let Self::Print(lines) = self else {
+            return Ok(());
+        };
         let config = context.persistent.combine(context.temporary.get(true));
-        let interval = config.interval;
+        if context.export.is_some() {
+            for line in lines {
+                let text = context.interpolate(line);
+                if let Some(script) = &mut context.export {
+                    script.print(&text);
+                }
+            }
+            return Ok(());
+        }
         context.elapsed += config.start_lag;
+        // A temporary seed applies to this instruction only
+        let mut seeded = config.seed.map(util::Rng::new);
 
-        if interval > 0 {
-            for character in self.0.chars() {
-                context.elapsed += interval;
-                cast.output(context.elapsed, character.encode_utf8(&mut [0u8; 4]))?;
+        for (index, line) in lines.iter().enumerate() {
+            if index > 0 {
+                cast.output(context.elapsed, "\r\n")?;
+                context.preview("\r\n");
+                context.elapsed += config.line_pause;
             }
-        } else {
-            cast.output(context.elapsed, &self.0)?;
+            let text = context.interpolate(line);
+            match config.print_pace {
+                Pace::Character => {
+                    let rng = seeded.as_mut().unwrap_or(&mut context.rng);
+                    type_text(&text, &config, &mut context.elapsed, rng, cast)?;
+                    context.elapsed += config.typing_interval();
+                }
+                Pace::Word(delay) => {
+                    for word in words(&text) {
+                        context.elapsed += delay;
+                        cast.output(context.elapsed, word)?;
+                    }
+                }
+                Pace::Line(delay) => {
+                    context.elapsed += delay;
+                    if !text.is_empty() {
+                        cast.output(context.elapsed, &text)?;
+                    }
+                }
+            }
+            context.preview(&text);
         }
-        context.preview(&self.0);
 
-        context.elapsed += interval;
         context.elapsed += config.end_lag;
         cast.output(context.elapsed, "\r\n")?;
         context.preview("\r\n");
@@ -47,16 +155,39 @@ impl InstructionTrait for PrintInstruction {
     }
 }
 
+/// Split given text into words, each with its following whitespace. Leading whitespace is kept with the first word.
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut previous_whitespace = false;
+    for (index, character) in text.char_indices() {
+        let whitespace = character.is_whitespace();
+        if previous_whitespace && !whitespace && start != index {
+            let leading = text[start..index].trim_start().is_empty();
+            if !leading {
+                words.push(&text[start..index]);
+                start = index;
+            }
+        }
+        previous_whitespace = whitespace;
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::instruction::Instruction;
 
     #[test]
     fn print_instruction() {
         let s = "Hello, world!";
         let mut context = ParseContext::new();
         let instruction = PrintInstruction::parse(s, &mut context).unwrap();
-        assert_eq!(instruction.0, s);
+        assert_eq!(instruction, PrintInstruction::Print(vec![s.to_string()]));
 
         let mut context = ExecutionContext::new();
         let mut writer = Vec::new();
@@ -85,4 +216,121 @@ mod tests {
         ];
         assert_eq!(lines_after, expected);
     }
+
+    #[test]
+    fn print_markup() {
+        let s = "[bold]Hi[/] [[x]";
+        let mut context = ParseContext::new();
+        let instruction = PrintInstruction::parse(s, &mut context).unwrap();
+        assert_eq!(instruction, PrintInstruction::Print(vec![s.to_string()]));
+
+        context.markup = true;
+        let instruction = PrintInstruction::parse(s, &mut context).unwrap();
+        assert_eq!(
+            instruction,
+            PrintInstruction::Print(vec!["\x1b[1mHi\x1b[0m [x]".to_string()])
+        );
+        assert_eq!(
+            PrintInstruction::parse("[bold", &mut context),
+            Err(ErrorType::MalformedInstruction)
+        );
+    }
+
+    #[test]
+    fn split_words() {
+        assert_eq!(words("  Hello,  world! "), ["  Hello,  ", "world! "]);
+        assert_eq!(words("one"), ["one"]);
+        assert!(words("").is_empty());
+    }
+
+    #[test]
+    fn print_pace() {
+        let instruction = PrintInstruction::Print(vec!["a b".to_string(), "c".to_string()]);
+        let cases = [
+            (
+                Pace::Word(40_000),
+                [
+                    r#"[0.040000,"o","a "]"#,
+                    r#"[0.080000,"o","b"]"#,
+                    r#"[0.080000,"o","\r\n"]"#,
+                    r#"[0.120000,"o","c"]"#,
+                    r#"[0.120000,"o","\r\n"]"#,
+                ]
+                .as_slice(),
+            ),
+            (
+                Pace::Line(150_000),
+                [
+                    r#"[0.150000,"o","a b"]"#,
+                    r#"[0.150000,"o","\r\n"]"#,
+                    r#"[0.300000,"o","c"]"#,
+                    r#"[0.300000,"o","\r\n"]"#,
+                ]
+                .as_slice(),
+            ),
+        ];
+        for (pace, expected) in cases {
+            let mut context = ExecutionContext::new();
+            context.persistent.print_pace = pace;
+            let mut writer = Vec::new();
+            instruction
+                .execute(&mut context, &mut AsciiCast::new(&mut writer))
+                .unwrap();
+
+            let output = String::from_utf8_lossy(&writer);
+            let lines_after = output.lines().skip(1).collect::<Vec<_>>();
+            assert_eq!(lines_after, expected, "Pace: {pace:?}");
+        }
+    }
+
+    #[test]
+    fn print_block() {
+        let mut context = ParseContext::new();
+        for (line, expected) in [
+            ("% <<EOF", PrintInstruction::Record),
+            ("  indented", PrintInstruction::Record),
+            ("$ not a command", PrintInstruction::Record),
+            (
+                " EOF ",
+                PrintInstruction::Print(vec![
+                    "  indented".to_string(),
+                    "$ not a command".to_string(),
+                ]),
+            ),
+        ] {
+            let instruction = Instruction::parse(line, &mut context).unwrap();
+            assert_eq!(instruction, Instruction::Print(expected), "Line: {line}");
+        }
+        assert!(context.print_block.is_none());
+
+        for malformed in ["<<", "<<A B"] {
+            assert_eq!(
+                PrintInstruction::parse(malformed, &mut context),
+                Err(ErrorType::MalformedInstruction)
+            );
+        }
+    }
+
+    #[test]
+    fn execute_print_block() {
+        let instruction = PrintInstruction::Print(vec!["a".to_string(), "b".to_string()]);
+        let mut context = ExecutionContext::new();
+        context.temporary.start_lag = Some(1_000_000);
+        context.temporary.end_lag = Some(1_000_000);
+        context.temporary.line_pause = Some(500_000);
+        let mut writer = Vec::new();
+        instruction
+            .execute(&mut context, &mut AsciiCast::new(&mut writer))
+            .unwrap();
+
+        let output = String::from_utf8_lossy(&writer);
+        let lines_after = output.lines().skip(1).collect::<Vec<_>>();
+        let expected = vec![
+            r#"[1.100000,"o","a"]"#,
+            r#"[1.200000,"o","\r\n"]"#,
+            r#"[1.800000,"o","b"]"#,
+            r#"[2.900000,"o","\r\n"]"#,
+        ];
+        assert_eq!(lines_after, expected);
+    }
 }
@@ -0,0 +1,238 @@
+//! Module for rendering dynamic prompt templates.
+
+use super::ExecutionContext;
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Render given prompt template with the state of the execution context. Supported placeholders:
+///
+/// - `{cwd}`: Current working directory, with the home directory replaced by `~`.
+/// - `{cwd_short}`: Last component of the current working directory.
+/// - `{status}`: Exit code of the last executed command.
+/// - `{user}`: Current user name.
+/// - `{host}`: Host name.
+/// - `{time}`: Current time in UTC, as `HH:MM:SS`.
+/// - `{git_branch}`: Current git branch, or the abbreviated commit hash if detached.
+///
+/// A conditional segment `{?name:content}` renders `content` only if the placeholder `name` is truthy, and `{!name:content}` only if it is not. `status` is truthy if non-zero, and other placeholders are truthy if non-empty. Unknown placeholders are kept as-is.
+pub fn render(template: &str, context: &ExecutionContext) -> String {
+    render_with(template, &|name| lookup(name, context))
+}
+
+/// Get the value of a placeholder, and whether it is truthy. Returns `None` for unknown placeholders.
+fn lookup(name: &str, context: &ExecutionContext) -> Option<(String, bool)> {
+    let value = match name {
+        "cwd" => home_relative(&context.directory),
+        "cwd_short" => {
+            let cwd = home_relative(&context.directory);
+            if cwd == "~" {
+                cwd
+            } else {
+                context.directory.file_name().map_or_else(
+                    || context.directory.display().to_string(),
+                    |name| name.to_string_lossy().to_string(),
+                )
+            }
+        }
+        "status" => {
+            let status = context.status;
+            return Some((status.to_string(), status != 0));
+        }
+        "user" => std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_default(),
+        "host" => host(),
+        "time" => {
+            let secs = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            clock(secs)
+        }
+        "git_branch" => git_branch(&context.directory).unwrap_or_default(),
+        _ => return None,
+    };
+    let truthy = !value.is_empty();
+    Some((value, truthy))
+}
+
+/// Render given template with given lookup function.
+fn render_with(template: &str, lookup: &impl Fn(&str) -> Option<(String, bool)>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        // Leave `${NAME}` (variables) alone
+        if rest[..start].ends_with('$') {
+            rendered.push_str(&rest[..=start]);
+            rest = &rest[start + 1..];
+            continue;
+        }
+        let after = &rest[start..];
+        let Some(end) = closing_brace(after) else {
+            // Unbalanced braces, keep the rest as-is
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        let inner = &after[1..end];
+        match placeholder(inner, lookup) {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&after[..=end]),
+        }
+        rest = &after[end + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Render the inner part of a placeholder (without braces), returning `None` if it is not a known placeholder.
+fn placeholder(inner: &str, lookup: &impl Fn(&str) -> Option<(String, bool)>) -> Option<String> {
+    let condition = match inner.chars().next() {
+        Some('?') => true,
+        Some('!') => false,
+        _ => return lookup(inner).map(|(value, _)| value),
+    };
+    let (name, content) = inner[1..].split_once(':')?;
+    let (_, truthy) = lookup(name)?;
+    Some(if truthy == condition {
+        render_with(content, lookup)
+    } else {
+        String::new()
+    })
+}
+
+/// Find the index of the brace closing the one at the start of given string.
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, character) in s.char_indices() {
+        match character {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Display given path, with the home directory replaced by `~`.
+fn home_relative(path: &Path) -> String {
+    if let Some(home) = std::env::var_os("HOME") {
+        if let Ok(relative) = path.strip_prefix(&home) {
+            return if relative.as_os_str().is_empty() {
+                "~".to_string()
+            } else {
+                format!("~/{}", relative.display())
+            };
+        }
+    }
+    path.display().to_string()
+}
+
+/// Get the host name.
+fn host() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Format seconds since the Unix epoch as `HH:MM:SS` in UTC.
+fn clock(secs: u64) -> String {
+    let secs = secs % 86_400;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Get the current git branch of given directory, or the abbreviated commit hash if detached.
+fn git_branch(directory: &Path) -> Option<String> {
+    let git = directory
+        .ancestors()
+        .map(|dir| dir.join(".git"))
+        .find(|git| git.exists())?;
+    let git = if git.is_file() {
+        // A worktree or submodule, pointing to the actual git directory
+        let pointer = fs::read_to_string(&git).ok()?;
+        let target = pointer.trim().strip_prefix("gitdir:")?.trim();
+        git.parent()?.join(target)
+    } else {
+        git
+    };
+    let head = fs::read_to_string(git.join("HEAD")).ok()?;
+    let head = head.trim();
+    Some(
+        head.strip_prefix("ref: refs/heads/")
+            .map_or_else(|| head.chars().take(7).collect(), ToString::to_string),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lookup with fixed values, for a failed command on a detached head.
+    fn fixed(name: &str) -> Option<(String, bool)> {
+        match name {
+            "cwd" => Some(("~/castwright".to_string(), true)),
+            "status" => Some(("1".to_string(), true)),
+            "git_branch" => Some((String::new(), false)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn placeholders() {
+        let cases = [
+            ("{cwd}$ ", "~/castwright$ "),
+            ("[{status}] {cwd}", "[1] ~/castwright"),
+            ("{unknown} {cwd", "{unknown} {cwd"),
+            ("${cwd} {cwd}", "${cwd} ~/castwright"),
+            (
+                "{?status:\u{1b}[31m{status}\u{1b}[0m }$ ",
+                "\u{1b}[31m1\u{1b}[0m $ ",
+            ),
+            ("{!status:ok}{?git_branch:({git_branch})}$ ", "$ "),
+            ("{!git_branch:no repo}", "no repo"),
+            ("{?unknown:x}", "{?unknown:x}"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(
+                render_with(template, &fixed),
+                expected,
+                "Template: {template}"
+            );
+        }
+    }
+
+    #[test]
+    fn context() {
+        let mut context = ExecutionContext::new();
+        context.status = 2;
+        assert_eq!(render("{status}{?status:!}", &context), "2!");
+        context.status = 0;
+        assert_eq!(render("{status}{?status:!}", &context), "0");
+        context.directory = "/".into();
+        assert_eq!(render("{cwd} {cwd_short}", &context), "/ /");
+    }
+
+    #[test]
+    fn time() {
+        assert_eq!(clock(0), "00:00:00");
+        assert_eq!(clock(86_400 + 3_723), "01:02:03");
+    }
+
+    #[test]
+    fn branch() {
+        // This crate is expected to be in a git repository
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR"));
+        if directory.join(".git").exists() {
+            assert!(git_branch(directory).is_some_and(|branch| !branch.is_empty()));
+        }
+        assert_eq!(git_branch(Path::new("/")), None);
+    }
+}
@@ -0,0 +1,142 @@
+//! Module for screen-control instructions.
+
+use super::{AsciiCast, ErrorType, ExecutionContext, InstructionTrait, ParseContext, util};
+
+/// A screen-control instruction, emitting escape sequences at the current time.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScreenInstruction {
+    /// Clear the screen and scrollback, and move the cursor home.
+    Clear,
+    /// Reset all text attributes.
+    Reset,
+    /// Set the window title (and icon name).
+    Title(String),
+    /// Move the cursor to the top-left corner.
+    Home,
+}
+
+impl ScreenInstruction {
+    /// Check if a trimmed line (without the leading `@`) is a screen-control instruction.
+    pub fn matches(s: &str) -> bool {
+        let keyword = s.split_whitespace().next().unwrap_or_default();
+        matches!(keyword, "clear" | "reset" | "title" | "home")
+    }
+    /// The escape sequence to emit.
+    fn sequence(&self, context: &ExecutionContext) -> String {
+        match self {
+            Self::Clear => "\x1b[H\x1b[2J\x1b[3J".to_string(),
+            Self::Reset => "\x1b[0m".to_string(),
+            Self::Title(title) => format!("\x1b]0;{}\x07", context.interpolate(title)),
+            Self::Home => "\x1b[H".to_string(),
+        }
+    }
+}
+
+impl InstructionTrait for ScreenInstruction {
+    /// Parse a trimmed line (without the leading `@`) into a `ScreenInstruction`.
+    fn parse(s: &str, context: &mut ParseContext) -> Result<Self, ErrorType> {
+        context.front_matter_state.end()?;
+        if context.expect_continuation {
+            return Err(ErrorType::ExpectedContinuation);
+        }
+
+        let (keyword, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let rest = rest.trim();
+        if keyword == "title" {
+            return Ok(Self::Title(util::parse_loose_string(rest)?));
+        }
+        if !rest.is_empty() {
+            return Err(ErrorType::MalformedInstruction);
+        }
+        match keyword {
+            "clear" => Ok(Self::Clear),
+            "reset" => Ok(Self::Reset),
+            "home" => Ok(Self::Home),
+            _ => Err(ErrorType::UnknownInstruction),
+        }
+    }
+    /// Execute the instruction.
+    fn execute(
+        &self,
+        context: &mut ExecutionContext,
+        cast: &mut AsciiCast<impl std::io::Write>,
+    ) -> Result<(), ErrorType> {
+        let sequence = self.sequence(context);
+        cast.output(context.elapsed, &sequence)?;
+        context.preview(&sequence);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_instruction() {
+        let instructions = [
+            ("clear", ScreenInstruction::Clear),
+            ("reset", ScreenInstruction::Reset),
+            ("home", ScreenInstruction::Home),
+            (
+                "title My Demo",
+                ScreenInstruction::Title("My Demo".to_string()),
+            ),
+            (
+                "title \"Padded \"",
+                ScreenInstruction::Title("Padded ".to_string()),
+            ),
+        ];
+        for (input, expected) in instructions {
+            assert!(ScreenInstruction::matches(input));
+            let mut context = ParseContext::new();
+            assert_eq!(
+                ScreenInstruction::parse(input, &mut context).unwrap(),
+                expected
+            );
+        }
+        for input in ["include a.cwrt", "clearly", "titles"] {
+            assert!(!ScreenInstruction::matches(input), "Input: {input}");
+        }
+    }
+
+    #[test]
+    fn malformed_screen_instruction() {
+        for input in ["clear now", "home 1"] {
+            let mut context = ParseContext::new();
+            assert!(matches!(
+                ScreenInstruction::parse(input, &mut context),
+                Err(ErrorType::MalformedInstruction)
+            ));
+        }
+    }
+
+    #[test]
+    fn execute_screen_instruction() {
+        let mut context = ExecutionContext::new();
+        context.elapsed = 1_500_000;
+        context
+            .variables
+            .insert("NAME".to_string(), "castwright".to_string());
+        let mut writer = Vec::new();
+        let mut cast = AsciiCast::new(&mut writer);
+        for instruction in [
+            ScreenInstruction::Title("Demo of ${NAME}".to_string()),
+            ScreenInstruction::Clear,
+        ] {
+            instruction.execute(&mut context, &mut cast).unwrap();
+        }
+        drop(cast);
+
+        let output = String::from_utf8(writer).unwrap();
+        let events: Vec<&str> = output.lines().skip(1).collect();
+        assert_eq!(
+            events,
+            [
+                r#"[1.500000,"o","\u001b]0;Demo of castwright\u0007"]"#,
+                r#"[1.500000,"o","\u001b[H\u001b[2J\u001b[3J"]"#,
+            ]
+        );
+        assert_eq!(context.elapsed, 1_500_000);
+    }
+}
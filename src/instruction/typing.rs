@@ -0,0 +1,613 @@
+//! Module for simulating typing, shared by command and print instructions.
+
+use super::{AsciiCast, Configuration, ErrorType, util::Rng};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Erase sequence for a single character: move back, overwrite with a space, and move back again.
+const ERASE: &str = "\x08 \x08";
+/// Input sent by the backspace key.
+const BACKSPACE: &str = "\x7f";
+/// Prefix of a deliberate mistake, e.g. `~{comit}` types `comit` and erases it.
+pub const MISTAKE_PREFIX: &str = "~{";
+
+/// Simulated typist, emitting keystrokes to the asciicast.
+struct Typist<'a, 'w, W: std::io::Write> {
+    /// The configuration to type with.
+    config: &'a Configuration,
+    /// Elapsed time in microseconds (µs).
+    elapsed: &'a mut u128,
+    /// Random number generator for jitter and typos.
+    rng: &'a mut Rng,
+    /// The asciicast to write to.
+    cast: &'a mut AsciiCast<'w, W>,
+    /// The previously typed character.
+    previous: Option<char>,
+    /// Whether the word being typed is a [common](COMMON_WORDS) one.
+    common: bool,
+    /// Whether to record keystrokes as input events.
+    record_input: bool,
+}
+
+impl<'a, 'w, W: std::io::Write> Typist<'a, 'w, W> {
+    /// Create a new typist.
+    const fn new(
+        config: &'a Configuration,
+        elapsed: &'a mut u128,
+        rng: &'a mut Rng,
+        cast: &'a mut AsciiCast<'w, W>,
+    ) -> Self {
+        Self {
+            config,
+            elapsed,
+            rng,
+            cast,
+            previous: None,
+            common: false,
+            record_input: false,
+        }
+    }
+    /// Type given text, optionally with simulated typos, returning the number of visible graphemes typed. Escape sequences are output at once without delay.
+    fn text(&mut self, text: &str, typos: bool) -> Result<usize, ErrorType> {
+        let mut count = 0;
+        let tokens = tokens(text);
+        for (index, token) in tokens.iter().enumerate() {
+            if let Token::Grapheme(grapheme) = token {
+                if self.previous.is_none_or(char::is_whitespace) && !grapheme.trim().is_empty() {
+                    self.common = is_common(&tokens[index..]);
+                }
+            }
+            match *token {
+                Token::Escape(sequence) => {
+                    self.cast.output(*self.elapsed, sequence)?;
+                    continue;
+                }
+                Token::Grapheme(grapheme) if typos => self.key_with_typo(grapheme)?,
+                Token::Grapheme(grapheme) => self.key(grapheme)?,
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+    /// Type a single extended grapheme cluster, so that combined characters (e.g. emoji sequences and combining accents) are never displayed partially.
+    fn key(&mut self, grapheme: &str) -> Result<(), ErrorType> {
+        let Some(first) = grapheme.chars().next() else {
+            return Ok(());
+        };
+        *self.elapsed += self.delay(first);
+        if self.record_input {
+            self.cast.input(*self.elapsed, grapheme)?;
+        }
+        self.cast.output(*self.elapsed, grapheme)?;
+        self.previous = grapheme.chars().next_back();
+        Ok(())
+    }
+    /// Erase given number of graphemes, one backspace at a time.
+    fn erase(&mut self, count: usize) -> Result<(), ErrorType> {
+        for _ in 0..count {
+            *self.elapsed += self.config.typing_interval();
+            if self.record_input {
+                self.cast.input(*self.elapsed, BACKSPACE)?;
+            }
+            self.cast.output(*self.elapsed, ERASE)?;
+        }
+        self.previous = None;
+        Ok(())
+    }
+    /// Type a grapheme, possibly mistyping it first according to the typo rate.
+    fn key_with_typo(&mut self, grapheme: &str) -> Result<(), ErrorType> {
+        let mut chars = grapheme.chars();
+        if let (Some(character), None) = (chars.next(), chars.next()) {
+            if self.config.typos > 0 && self.rng.chance(self.config.typos) {
+                if let Some(typo) = neighbour(character, self.rng) {
+                    // https://stackoverflow.com/a/67898224/16468609
+                    self.key(typo.encode_utf8(&mut [0u8; 4]))?;
+                    self.erase(1)?;
+                }
+            }
+        }
+        self.key(grapheme)
+    }
+    /// Calculate the delay before typing given character.
+    fn delay(&mut self, character: char) -> u128 {
+        let config = self.config;
+        let mut delay = config.typing_interval();
+        if config.typing_model() {
+            delay = delay * wpm_factor(self.previous, character, self.common) / 100;
+        }
+        if config.jitter > 0 {
+            // Vary the interval by at most `jitter` percent
+            let offset = self.rng.next_offset(config.jitter);
+            let percent = u128::try_from(100 + offset).expect("Jitter is at most 100%");
+            delay = delay * percent / 100;
+        }
+        if self
+            .previous
+            .is_some_and(|c| c.is_whitespace() || c.is_ascii_punctuation())
+        {
+            delay += config.pause;
+        }
+        delay
+    }
+}
+
+/// Shifted characters on a US keyboard, other than uppercase letters.
+const SHIFTED: &str = "~!@#$%^&*()_+{}|:\"<>?";
+
+/// Frequently typed words, which flow faster than others in the words-per-minute typing model.
+const COMMON_WORDS: &[&str] = &[
+    "a", "and", "are", "be", "cat", "cd", "echo", "for", "git", "i", "in", "is", "it", "ls", "not",
+    "of", "on", "or", "that", "the", "this", "to", "with", "you",
+];
+
+/// Check whether the word at the start of given tokens is [common](COMMON_WORDS), ignoring case, escape sequences and surrounding punctuation.
+fn is_common(tokens: &[Token]) -> bool {
+    let word: String = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Grapheme(grapheme) => Some(*grapheme),
+            Token::Escape(_) => None,
+        })
+        .take_while(|grapheme| grapheme.trim().len() == grapheme.len())
+        .collect();
+    let word = word.trim_matches(|c: char| c.is_ascii_punctuation());
+    COMMON_WORDS.contains(&word.to_lowercase().as_str())
+}
+
+/// Relative delay before typing given character in the words-per-minute typing model, in percent of the average interval. Letters inside a word flow faster, even more so in a common word, while the first character of a word, digits and shifted characters are slower.
+fn wpm_factor(previous: Option<char>, character: char, common: bool) -> u128 {
+    if character.is_ascii_uppercase() || SHIFTED.contains(character) {
+        160
+    } else if character.is_ascii_digit() {
+        140
+    } else if previous.is_none_or(char::is_whitespace) || character.is_whitespace() {
+        // Word boundary
+        130
+    } else if common {
+        60
+    } else {
+        80
+    }
+}
+
+/// Type given text grapheme by grapheme, advancing `elapsed` by the configured interval before each grapheme. If the interval is `0`, the text is output at once.
+pub fn type_text(
+    text: &str,
+    config: &Configuration,
+    elapsed: &mut u128,
+    rng: &mut Rng,
+    cast: &mut AsciiCast<impl std::io::Write>,
+) -> Result<(), ErrorType> {
+    if config.typing_interval() == 0 {
+        cast.output(*elapsed, text)?;
+        return Ok(());
+    }
+    let mut typist = Typist::new(config, elapsed, rng, cast);
+    typist.text(text, false)?;
+    Ok(())
+}
+
+/// Type given command like [`type_text`], but with simulated typos and deliberate mistakes (`~{...}`), which are typed and then erased. If the interval is `0`, only the corrected command is output. If `record_input` is set, keystrokes are also recorded as input events.
+pub fn type_command(
+    command: &str,
+    config: &Configuration,
+    elapsed: &mut u128,
+    rng: &mut Rng,
+    cast: &mut AsciiCast<impl std::io::Write>,
+    record_input: bool,
+) -> Result<(), ErrorType> {
+    if config.typing_interval() == 0 {
+        let command = strip_mistakes(command);
+        if record_input {
+            cast.input(*elapsed, &command)?;
+        }
+        cast.output(*elapsed, &command)?;
+        return Ok(());
+    }
+    let mut typist = Typist::new(config, elapsed, rng, cast);
+    typist.record_input = record_input;
+    for segment in segments(command) {
+        match segment {
+            Segment::Text(text) => {
+                typist.text(text, true)?;
+            }
+            Segment::Mistake(mistake) => {
+                let count = typist.text(mistake, false)?;
+                typist.erase(count)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A token of typed text.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Token<'a> {
+    /// An escape sequence, which is invisible and thus output without delay.
+    Escape(&'a str),
+    /// A visible extended grapheme cluster.
+    Grapheme(&'a str),
+}
+
+/// Split given text into escape sequences and graphemes.
+fn tokens(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while let Some(index) = rest.find('\x1b') {
+        tokens.extend(rest[..index].graphemes(true).map(Token::Grapheme));
+        let len = escape_len(&rest[index..]);
+        tokens.push(Token::Escape(&rest[index..index + len]));
+        rest = &rest[index + len..];
+    }
+    tokens.extend(rest.graphemes(true).map(Token::Grapheme));
+    tokens
+}
+
+/// Get the length in bytes of the escape sequence at the start of given string, which starts with `ESC`. Recognizes CSI sequences (`ESC [ ... final`), OSC sequences (`ESC ] ...`, terminated by `BEL` or `ESC \`) and two-character sequences. An unterminated sequence spans the rest of the string.
+fn escape_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    match bytes.get(1) {
+        // CSI: parameter and intermediate bytes, followed by a final byte
+        Some(b'[') => bytes[2..]
+            .iter()
+            .position(|b| (0x40..=0x7E).contains(b))
+            .map_or(bytes.len(), |end| end + 3),
+        // OSC: terminated by `BEL` or `ST` (`ESC \`)
+        Some(b']') => {
+            let body = &s[2..];
+            let bel = body.find('\x07').map(|end| end + 1);
+            let st = body.find("\x1b\\").map(|end| end + 2);
+            match (bel, st) {
+                (Some(bel), Some(st)) => bel.min(st) + 2,
+                (Some(end), None) | (None, Some(end)) => end + 2,
+                (None, None) => bytes.len(),
+            }
+        }
+        // Two-character sequence, e.g. `ESC c`
+        Some(_) => 1 + s[1..].chars().next().map_or(0, char::len_utf8),
+        // Lone `ESC`
+        None => 1,
+    }
+}
+
+/// Remove deliberate mistakes (`~{...}`) from given command, returning the command to execute.
+pub fn strip_mistakes(command: &str) -> String {
+    segments(command)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Text(text) => Some(text),
+            Segment::Mistake(_) => None,
+        })
+        .collect()
+}
+
+/// A segment of a command.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// Text to type.
+    Text(&'a str),
+    /// A deliberate mistake, to be typed and erased.
+    Mistake(&'a str),
+}
+
+/// Split given command into segments of text and deliberate mistakes. `~~{` is an escaped `~{`, and an unclosed `~{` is treated as text.
+pub fn segments(command: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = command;
+    while let Some(index) = rest.find(MISTAKE_PREFIX) {
+        if rest[..index].ends_with('~') {
+            // Escaped: keep everything up to (excluding) the escaping `~`
+            segments.push(Segment::Text(&rest[..index - 1]));
+            segments.push(Segment::Text(MISTAKE_PREFIX));
+            rest = &rest[index + MISTAKE_PREFIX.len()..];
+            continue;
+        }
+        let after = &rest[index + MISTAKE_PREFIX.len()..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        segments.push(Segment::Text(&rest[..index]));
+        segments.push(Segment::Mistake(&after[..end]));
+        rest = &after[end + 1..];
+    }
+    segments.push(Segment::Text(rest));
+    segments.retain(|segment| *segment != Segment::Text(""));
+    segments
+}
+
+/// Pick a key next to given character on a QWERTY keyboard, preserving case. Returns `None` for characters that are not ASCII letters.
+fn neighbour(character: char, rng: &mut Rng) -> Option<char> {
+    const ROWS: [&str; 3] = ["qwertyuiop", "asdfghjkl", "zxcvbnm"];
+    let lower = character.to_ascii_lowercase();
+    let row = ROWS.iter().find(|row| row.contains(lower))?.as_bytes();
+    let index = row.iter().position(|&c| c == lower as u8)?;
+    let typo = if index == 0 || (index + 1 < row.len() && rng.chance(50)) {
+        row[index + 1]
+    } else {
+        row[index - 1]
+    };
+    let typo = char::from(typo);
+    Some(if character.is_ascii_uppercase() {
+        typo.to_ascii_uppercase()
+    } else {
+        typo
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Type given text, returning the timestamps of output events.
+    fn timestamps(text: &str, config: &Configuration, seed: u64) -> Vec<u128> {
+        let mut writer = Vec::new();
+        let mut elapsed = 0;
+        type_text(
+            text,
+            config,
+            &mut elapsed,
+            &mut Rng::new(seed),
+            &mut AsciiCast::new(&mut writer),
+        )
+        .unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        output
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let time = line[1..].split(',').next().unwrap();
+                let (secs, micros) = time.split_once('.').unwrap();
+                secs.parse::<u128>().unwrap() * 1_000_000 + micros.parse::<u128>().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn constant_interval() {
+        let config = Configuration::default();
+        assert_eq!(
+            timestamps("abc", &config, 0),
+            vec![100_000, 200_000, 300_000]
+        );
+    }
+
+    #[test]
+    fn graphemes() {
+        let config = Configuration::default();
+        // Family emoji (ZWJ sequence), flag, and `e` with combining acute accent
+        assert_eq!(
+            timestamps("👨‍👩‍👧🇨🇳e\u{301}", &config, 0),
+            vec![100_000, 200_000, 300_000]
+        );
+    }
+
+    #[test]
+    fn escape_sequences() {
+        let cases = [
+            (
+                "\x1b[36mab",
+                vec![
+                    Token::Escape("\x1b[36m"),
+                    Token::Grapheme("a"),
+                    Token::Grapheme("b"),
+                ],
+            ),
+            (
+                "a\x1b[2;36m\x1b[0m",
+                vec![
+                    Token::Grapheme("a"),
+                    Token::Escape("\x1b[2;36m"),
+                    Token::Escape("\x1b[0m"),
+                ],
+            ),
+            (
+                "\x1b]0;title\x07a",
+                vec![Token::Escape("\x1b]0;title\x07"), Token::Grapheme("a")],
+            ),
+            (
+                "\x1b]2;title\x1b\\a",
+                vec![Token::Escape("\x1b]2;title\x1b\\"), Token::Grapheme("a")],
+            ),
+            ("\x1bca", vec![Token::Escape("\x1bc"), Token::Grapheme("a")]),
+            (
+                "a\x1b[3",
+                vec![Token::Grapheme("a"), Token::Escape("\x1b[3")],
+            ),
+            ("a\x1b", vec![Token::Grapheme("a"), Token::Escape("\x1b")]),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(tokens(text), expected, "Text: {text:?}");
+        }
+
+        let config = Configuration::default();
+        assert_eq!(
+            timestamps("\x1b[1ma\x1b[0mb", &config, 0),
+            vec![0, 100_000, 100_000, 200_000]
+        );
+    }
+
+    #[test]
+    fn jitter() {
+        let config = Configuration {
+            jitter: 30,
+            ..Configuration::default()
+        };
+        let first = timestamps("Hello, world!", &config, 42);
+        let second = timestamps("Hello, world!", &config, 42);
+        let other = timestamps("Hello, world!", &config, 43);
+        assert_eq!(first, second, "Same seed should be reproducible");
+        assert_ne!(first, other, "Different seeds should differ");
+
+        let mut previous = 0;
+        for time in first {
+            let delay = time - previous;
+            assert!((70_000..=130_000).contains(&delay), "Delay: {delay}");
+            previous = time;
+        }
+    }
+
+    #[test]
+    fn pause() {
+        let config = Configuration {
+            pause: 50_000,
+            ..Configuration::default()
+        };
+        assert_eq!(
+            timestamps("a, b", &config, 0),
+            vec![100_000, 200_000, 350_000, 500_000]
+        );
+    }
+
+    /// Type given command, returning the raw asciicast output (without header).
+    fn typed_command(command: &str, config: &Configuration, seed: u64) -> String {
+        let mut writer = Vec::new();
+        let mut elapsed = 0;
+        type_command(
+            command,
+            config,
+            &mut elapsed,
+            &mut Rng::new(seed),
+            &mut AsciiCast::new(&mut writer),
+            false,
+        )
+        .unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        output
+            .lines()
+            .skip(1)
+            .map(|line| line.rsplit_once(",\"").unwrap().1.trim_end_matches("\"]"))
+            .collect()
+    }
+
+    #[test]
+    fn mistake_segments() {
+        let cases = [
+            ("git commit", vec![Segment::Text("git commit")]),
+            (
+                "git ~{comit}commit",
+                vec![
+                    Segment::Text("git "),
+                    Segment::Mistake("comit"),
+                    Segment::Text("commit"),
+                ],
+            ),
+            (
+                "echo ~~{x}",
+                vec![
+                    Segment::Text("echo "),
+                    Segment::Text("~{"),
+                    Segment::Text("x}"),
+                ],
+            ),
+            ("echo ~{unclosed", vec![Segment::Text("echo ~{unclosed")]),
+        ];
+        for (command, expected) in cases {
+            assert_eq!(segments(command), expected, "Command: {command}");
+        }
+        assert_eq!(strip_mistakes("git ~{comit}commit"), "git commit");
+        assert_eq!(strip_mistakes("echo ~~{x}"), "echo ~{x}");
+    }
+
+    #[test]
+    fn deliberate_mistake() {
+        let config = Configuration::default();
+        assert_eq!(typed_command("l~{s}s", &config, 0), "ls\\b \\bs");
+        let zero = Configuration {
+            interval: Some(0),
+            ..Configuration::default()
+        };
+        assert_eq!(typed_command("l~{s}s", &zero, 0), "ls");
+    }
+
+    #[test]
+    fn record_input() {
+        let config = Configuration::default();
+        let mut writer = Vec::new();
+        let mut elapsed = 0;
+        type_command(
+            "l~{s}s",
+            &config,
+            &mut elapsed,
+            &mut Rng::new(0),
+            &mut AsciiCast::new(&mut writer),
+            true,
+        )
+        .unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        let inputs: Vec<&str> = output
+            .lines()
+            .filter(|line| line.contains(",\"i\","))
+            .collect();
+        assert_eq!(
+            inputs,
+            vec![
+                r#"[0.100000,"i","l"]"#,
+                r#"[0.200000,"i","s"]"#,
+                "[0.300000,\"i\",\"\x7f\"]",
+                r#"[0.400000,"i","s"]"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn typos() {
+        let config = Configuration {
+            typos: 100,
+            ..Configuration::default()
+        };
+        let typed = typed_command("ab 1", &config, 42);
+        let erase = "\\b \\b";
+        // Every letter is mistyped and corrected, other characters are not
+        let parts: Vec<&str> = typed.split(erase).collect();
+        assert_eq!(parts.len(), 3, "Typed: {typed}");
+        assert!(parts[1].ends_with('v') || parts[1].ends_with('n'));
+        assert!(typed.ends_with("b 1"), "Typed: {typed}");
+        assert_eq!(typed, typed_command("ab 1", &config, 42));
+    }
+
+    #[test]
+    fn neighbours() {
+        let mut rng = Rng::new(0);
+        assert_eq!(neighbour('q', &mut rng), Some('w'));
+        assert_eq!(neighbour('M', &mut rng), Some('N'));
+        assert_eq!(neighbour('1', &mut rng), None);
+        for _ in 0..10 {
+            assert!(matches!(neighbour('s', &mut rng), Some('a' | 'd')));
+        }
+    }
+
+    #[test]
+    fn wpm() {
+        let config = Configuration {
+            wpm: 60,
+            ..Configuration::default()
+        };
+        assert_eq!(
+            timestamps("lz A1", &config, 0),
+            // Word start, letter, space, shifted, digit
+            vec![260_000, 420_000, 680_000, 1_000_000, 1_280_000]
+        );
+        assert_eq!(
+            timestamps("The", &config, 0),
+            // Word start, letters in a common word
+            vec![320_000, 440_000, 560_000]
+        );
+        assert!(is_common(&tokens("\x1b[1mgit,\x1b[0m commit")));
+        assert!(!is_common(&tokens("github")));
+
+        // An explicit interval takes precedence over the typing model
+        let config = Configuration {
+            interval: Some(200_000),
+            ..config
+        };
+        assert_eq!(timestamps("lz", &config, 0), vec![200_000, 400_000]);
+    }
+
+    #[test]
+    fn zero_interval() {
+        let config = Configuration {
+            interval: Some(0),
+            ..Configuration::default()
+        };
+        assert_eq!(timestamps("abc", &config, 0), vec![0]);
+    }
+}
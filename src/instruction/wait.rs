@@ -24,6 +24,9 @@ impl InstructionTrait for WaitInstruction {
         context: &mut ExecutionContext,
         _cast: &mut AsciiCast<impl std::io::Write>,
     ) -> Result<(), ErrorType> {
+        if let Some(script) = &mut context.export {
+            script.wait(self.0);
+        }
         context.elapsed += self.0;
         Ok(())
     }
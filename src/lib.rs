@@ -6,7 +6,7 @@
 //!
 //! ## Usage
 //!
-//! Mostly, you'll deal with the [`CastWright`] struct and [`Error`] struct. When you want to you manually create errors, you need to deal with the [`ErrorType`] enum. If you're writing your own tool for generating asciicasts, you can use the [`AsciiCast`] struct.
+//! Mostly, you'll deal with the [`CastWright`] struct and [`Error`] struct. When you want to you manually create errors, you need to deal with the [`ErrorType`] enum. If you're writing your own tool for generating asciicasts, you can use the [`AsciiCast`] struct. To read existing asciicasts (v2 or v3), use the [`Reader`] struct, which parses the [`Header`] and yields [`TimedEvent`]s. To convert an existing asciicast or VHS tape into a script, use [`import_cast`] or [`import_tape`].
 //!
 //! ## Example
 //!
@@ -36,22 +36,31 @@
 #![warn(clippy::all, clippy::nursery, clippy::pedantic, clippy::cargo)]
 
 mod asciicast;
+mod convert;
 mod error;
 mod instruction;
 mod shell;
 mod util;
 
-pub use asciicast::AsciiCast;
+pub use asciicast::{
+    AsciiCast, EventKind, Format, Header, Reader, TermTheme, TimedEvent, cap_idle, concat, cut,
+    speed,
+};
+use convert::ShellScript;
+pub use convert::{DEFAULT_PROMPT, import_cast, import_tape};
 pub use error::{Error, ErrorType};
-use instruction::{Instruction, InstructionTrait};
+use instruction::{Instruction, InstructionTrait, Pace, Theme};
 use optfield::optfield;
 use shell::execute_command;
 use util::get_terminal_size;
 use std::{
     borrow::Cow,
-    io::{BufRead, Write},
-    path::PathBuf,
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
 };
+pub use util::is_valid_variable_name;
 
 /// The version of the `CastWright` library.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION", "can't determine version");
@@ -109,12 +118,34 @@ struct Configuration {
     hidden: bool,
     /// Expected exit status of the command. `true` for success, `false` for failure, `None` for any.
     expect: Option<bool>,
-    /// Typing interval between characters in a command or print instruction, in microseconds (µs).
-    interval: u128,
+    /// Typing interval between characters in a command or print instruction, in microseconds (µs). `None` if not set explicitly, see [`Configuration::typing_interval`].
+    interval: Option<u128>,
     /// The start lag in microseconds (µs). i.e. Additional delay after displaying the prompt, before printing the command for command instructions, or before printing the content for print instructions.
     start_lag: u128,
     /// The end lag in microseconds (µs). i.e. Additional delay after printing the command for command instructions, or after printing the content for print instructions.
     end_lag: u128,
+    /// Maximum random variation of the typing interval, in percent of the interval.
+    jitter: u8,
+    /// Additional delay after typing a whitespace or punctuation character, in microseconds (µs).
+    pause: u128,
+    /// Probability of mistyping a letter in a command, in percent.
+    typos: u8,
+    /// Seed for a random number generator used by this instruction only, instead of the shared one. Only set temporarily.
+    seed: Option<u64>,
+    /// Typing speed in words per minute, varying the interval per character with a typing model. `0` if disabled. Has no effect if `interval` is set explicitly.
+    wpm: u16,
+    /// Whether to paste the command at once instead of typing it. `None` for typing, `Some(false)` for plain paste, `Some(true)` for bracketed paste.
+    paste: Option<bool>,
+    /// Delay after pasting a command, before pressing Enter, in microseconds (µs).
+    paste_pause: u128,
+    /// Whether to highlight the shell syntax of commands.
+    highlight: bool,
+    /// Template of the line to print after a command exits with a non-zero status. Empty if disabled.
+    status_indicator: String,
+    /// Additional delay after each line of a print block but the last, in microseconds (µs).
+    line_pause: u128,
+    /// How the content of print instructions is revealed.
+    print_pace: Pace,
 }
 
 impl Configuration {
@@ -122,8 +153,20 @@ impl Configuration {
     fn new() -> Self {
         Self::default()
     }
+    /// The typing interval in microseconds (µs): the explicit `interval` if set, otherwise the average interval for `wpm` (taking a word as 5 characters) if enabled, or 100ms.
+    fn typing_interval(&self) -> u128 {
+        match (self.interval, self.wpm) {
+            (Some(interval), _) => interval,
+            (None, 0) => 100_000,
+            (None, wpm) => 60_000_000 / (5 * u128::from(wpm)),
+        }
+    }
+    /// Whether the words-per-minute typing model is used, i.e. `wpm` is enabled and no explicit `interval` takes precedence.
+    const fn typing_model(&self) -> bool {
+        self.interval.is_none() && self.wpm > 0
+    }
     /// Combine with a temporary configuration.
-    fn combine(&self, temporary: TemporaryConfiguration) -> Cow<Self> {
+    fn combine(&self, temporary: TemporaryConfiguration) -> Cow<'_, Self> {
         if temporary.is_empty() {
             // No temporary configuration - use a borrowed reference to the persistent configuration
             Cow::Borrowed(self)
@@ -144,9 +187,20 @@ impl Default for Configuration {
             line_continuation: " \\".to_string(),
             hidden: false,
             expect: Some(true),
-            interval: 100_000,
+            interval: None,
             start_lag: 0,
             end_lag: 0,
+            jitter: 0,
+            pause: 0,
+            typos: 0,
+            seed: None,
+            wpm: 0,
+            paste: None,
+            paste_pause: 0,
+            highlight: false,
+            status_indicator: String::new(),
+            line_pause: 0,
+            print_pace: Pace::Character,
         }
     }
 }
@@ -166,6 +220,17 @@ impl TemporaryConfiguration {
             && self.interval.is_none()
             && self.start_lag.is_none()
             && self.end_lag.is_none()
+            && self.jitter.is_none()
+            && self.pause.is_none()
+            && self.typos.is_none()
+            && self.seed.is_none()
+            && self.wpm.is_none()
+            && self.paste.is_none()
+            && self.paste_pause.is_none()
+            && self.highlight.is_none()
+            && self.status_indicator.is_none()
+            && self.line_pause.is_none()
+            && self.print_pace.is_none()
     }
     /// Take or clone self, depending on the `consume` parameter.
     fn get(&mut self, consume: bool) -> Self {
@@ -185,21 +250,65 @@ struct ParseContext {
     start: char,
     /// Whether we're expecting a continuation.
     expect_continuation: bool,
+    /// Defined macros, mapping names to lines of their bodies.
+    macros: HashMap<String, Vec<String>>,
+    /// The macro being defined, with its name and lines recorded so far.
+    recording: Option<(String, Vec<String>)>,
+    /// Depth of conditional blocks in the macro being defined, so that their `@end` is recorded.
+    recording_depth: usize,
+    /// Closing delimiter of the print block open in the macro being defined, so that its lines are recorded as they are.
+    recording_block: Option<String>,
+    /// Names of macros being expanded, used to detect recursion.
+    expanding: Vec<String>,
+    /// Open conditional blocks, each denoting whether it has an `@else` branch.
+    conditionals: Vec<bool>,
+    /// The print block being recorded, with its closing delimiter and lines recorded so far.
+    print_block: Option<(String, Vec<String>)>,
+    /// Whether inline style markup is enabled for print instructions.
+    markup: bool,
 }
 
 impl ParseContext {
     /// Create a new `ParseContext`.
-    const fn new() -> Self {
+    fn new() -> Self {
         Self {
             front_matter_state: FrontMatterState::None,
             start: ' ',
             expect_continuation: false,
+            macros: HashMap::new(),
+            recording: None,
+            recording_depth: 0,
+            recording_block: None,
+            expanding: Vec::new(),
+            conditionals: Vec::new(),
+            print_block: None,
+            markup: false,
+        }
+    }
+    /// Check that the script has not ended in the middle of the front matter, a multiline command, a macro definition, a conditional block or a print block.
+    const fn finish(&self) -> Result<(), ErrorType> {
+        if matches!(self.front_matter_state, FrontMatterState::Start) {
+            Err(ErrorType::ExpectedClosingDelimiter)
+        } else if self.expect_continuation {
+            Err(ErrorType::ExpectedContinuation)
+        } else if self.recording.is_some()
+            || !self.conditionals.is_empty()
+            || self.print_block.is_some()
+        {
+            Err(ErrorType::ExpectedEnd)
+        } else {
+            Ok(())
         }
     }
     /// Create a context with a different starting character.
     #[cfg(test)]
-    const fn with_start(&self, start: char) -> Self {
-        Self { start, ..*self }
+    fn with_start(&self, start: char) -> Self {
+        Self {
+            front_matter_state: self.front_matter_state,
+            start,
+            expect_continuation: self.expect_continuation,
+            ..Self::new()
+        }
     }
 }
 
@@ -216,6 +325,12 @@ struct ExecutionContext {
     directory: PathBuf,
     /// Elapsed time in microseconds (µs).
     elapsed: u128,
+    /// Open conditional blocks, each denoting whether its current branch is active.
+    branches: Vec<bool>,
+    /// Pseudo-random number generator for typing, seeded by the front matter.
+    rng: util::Rng,
+    /// Theme for syntax highlighting.
+    theme: Theme,
     /// Current terminal width.
     width: u16,
     /// Current terminal height.
@@ -226,10 +341,20 @@ struct ExecutionContext {
     execute: bool,
     /// Whether to preview the asciicast.
     preview: bool,
+    /// Whether to record simulated keystrokes as input events.
+    record_input: bool,
+    /// Variables injected from outside the script, taking precedence over `variables`.
+    injected: HashMap<String, String>,
+    /// The shell script being exported, in place of executing commands and writing the asciicast.
+    export: Option<ShellScript>,
 
     // Instruction-specific
+    /// Variables defined by `@@set` instructions.
+    variables: HashMap<String, String>,
     /// Previous commands to be concatenated.
     command: String,
+    /// Exit code of the last executed command.
+    status: i32,
 }
 
 impl ExecutionContext {
@@ -243,14 +368,37 @@ impl ExecutionContext {
                 .canonicalize()
                 .expect("Failed to canonicalize current directory"),
             elapsed: 0,
+            branches: Vec::new(),
+            rng: util::Rng::new(0),
+            theme: Theme::default(),
             width: 80,
             height: 24,
             execute: false,
             preview: false,
+            record_input: false,
+            injected: HashMap::new(),
+            export: None,
+            variables: HashMap::new(),
             command: String::new(),
+            status: 0,
         }
     }
 
+    /// Whether instructions should be skipped, since the current conditional branch is inactive.
+    fn skipping(&self) -> bool {
+        self.branches.last() == Some(&false)
+    }
+
+    /// Interpolate `${NAME}` occurrences in given string with defined variables.
+    fn interpolate(&self, s: &str) -> String {
+        util::interpolate(s, |name| {
+            self.injected
+                .get(name)
+                .or_else(|| self.variables.get(name))
+                .map(String::as_str)
+        })
+    }
+
     /// Print given string if preview is enabled.
     fn preview(&self, s: &str) {
         if self.preview {
@@ -274,11 +422,18 @@ impl ExecutionContext {
 /// - [`execute`](`CastWright::execute`): Set whether to execute and capture the output of shell commands.
 /// - [`timestamp`](`CastWright::timestamp`): Set whether to include timestamp information in the output.
 /// - [`preview`](`CastWright::preview`): Set whether to preview the asciicast.
+/// - [`record_input`](`CastWright::record_input`): Set whether to record simulated keystrokes as input events.
+/// - [`format`](`CastWright::format`): Set the version of the asciicast format to write.
+/// - [`variable`](`CastWright::variable`): Define a variable, which takes precedence over `@@set` in the script.
+/// - [`export`](`CastWright::export`): Set whether to write a POSIX shell script of the commands, instead of an asciicast.
+/// - [`sleep`](`CastWright::sleep`): Set whether waits are exported as `sleep` commands, instead of comments.
 ///
 /// ## Running
 ///
 /// To parse and execute a `CastWright` script and write the resulting asciicast, use the [`run`](`CastWright::run`) method, which takes mutable references to a reader and a writer. For better performance, a buffered writer is recommended.
 ///
+/// If the script is stored in a file, you can use the [`run_file`](`CastWright::run_file`) method instead, so that `@include` instructions are resolved relative to the script.
+///
 /// ## Example
 ///
 /// ```rust
@@ -315,6 +470,7 @@ impl ExecutionContext {
 /// let asciicast = String::from_utf8_lossy(&writer);
 /// ```
 #[derive(Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct CastWright {
     /// Whether to execute and capture the output of shell commands.
     execute: bool,
@@ -322,6 +478,16 @@ pub struct CastWright {
     timestamp: bool,
     /// Whether to preview the asciicast.
     preview: bool,
+    /// Whether to record simulated keystrokes as input events.
+    record_input: bool,
+    /// Version of the asciicast format to write.
+    format: Format,
+    /// Variables injected into the script.
+    variables: HashMap<String, String>,
+    /// Whether to write a POSIX shell script instead of an asciicast.
+    export: bool,
+    /// Whether to export waits as `sleep` commands instead of comments.
+    sleep: bool,
 }
 
 impl CastWright {
@@ -332,34 +498,108 @@ impl CastWright {
     }
     /// Set whether to execute and capture the output of shell commands.
     #[must_use]
-    pub const fn execute(self, execute: bool) -> Self {
-        Self { execute, ..self }
+    pub const fn execute(mut self, execute: bool) -> Self {
+        self.execute = execute;
+        self
     }
     /// Set whether to include timestamp information in the output.
     #[must_use]
-    pub const fn timestamp(self, timestamp: bool) -> Self {
-        Self { timestamp, ..self }
+    pub const fn timestamp(mut self, timestamp: bool) -> Self {
+        self.timestamp = timestamp;
+        self
     }
     /// Set whether to preview the asciicast.
     #[must_use]
-    pub const fn preview(self, preview: bool) -> Self {
-        Self { preview, ..self }
+    pub const fn preview(mut self, preview: bool) -> Self {
+        self.preview = preview;
+        self
+    }
+    /// Set whether to record simulated keystrokes of commands as input events, including the Enter key. Can also be enabled by the `record-input` key in the front matter.
+    #[must_use]
+    pub const fn record_input(mut self, record_input: bool) -> Self {
+        self.record_input = record_input;
+        self
+    }
+    /// Set the version of the asciicast format to write, defaulting to [v2](Format::V2). With [v3](Format::V3), the exit status of the last executed command is also recorded as an exit event at the end, if commands are [executed](CastWright::execute).
+    #[must_use]
+    pub const fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
     }
-    /// Interpret and run a `CastWright` script from a reader, writing the asciicast to a writer.
+    /// Define a variable, which can be referenced as `${NAME}` in the script. Variables defined this way take precedence over those defined by `@@set` instructions. Names that are not [valid](is_valid_variable_name) are never interpolated.
+    #[must_use]
+    pub fn variable(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.variables.insert(name.into(), value.into());
+        self
+    }
+    /// Set whether to write a POSIX shell script instead of an asciicast. Commands (joined with their continuations, including hidden ones) are written as they would be executed, guarded by `|| true` unless expected to succeed, while print instructions become `printf` and markers and waits become comments. Commands are not executed, regardless of [`execute`](`CastWright::execute`). Conditional blocks are resolved on the exporting host, so only their active branches are written.
+    #[must_use]
+    pub const fn export(mut self, export: bool) -> Self {
+        self.export = export;
+        self
+    }
+    /// Set whether waits are exported as `sleep` commands instead of comments, rounded up to whole seconds as POSIX `sleep` requires. Only takes effect with [`export`](`CastWright::export`).
+    #[must_use]
+    pub const fn sleep(mut self, sleep: bool) -> Self {
+        self.sleep = sleep;
+        self
+    }
+    /// Interpret and run a `CastWright` script from a reader, writing the asciicast to a writer. Included scripts are resolved relative to the current working directory.
     ///
     /// ## Errors
     ///
     /// This method returns an error if the script contains any syntax errors, or any errors occur during execution.
     pub fn run(&self, reader: &mut impl BufRead, writer: &mut impl Write) -> Result<(), Error> {
+        self.run_with(reader, None, writer)
+    }
+    /// Interpret and run a `CastWright` script from a file, writing the asciicast to a writer. Included scripts are resolved relative to the directory of the including script.
+    ///
+    /// ## Errors
+    ///
+    /// This method returns an error if the file cannot be read, the script contains any syntax errors, or any errors occur during execution.
+    pub fn run_file(&self, path: impl AsRef<Path>, writer: &mut impl Write) -> Result<(), Error> {
+        let path = path
+            .as_ref()
+            .canonicalize()
+            .map_err(|e| ErrorType::Io(e).with_line(0))?;
+        let file = File::open(&path).map_err(|e| ErrorType::Io(e).with_line(0))?;
+        self.run_with(&mut BufReader::new(file), Some(path), writer)
+    }
+    /// Interpret and run a `CastWright` script from a reader, with an optional canonical path of the script.
+    fn run_with(
+        &self,
+        reader: &mut impl BufRead,
+        path: Option<PathBuf>,
+        writer: &mut impl Write,
+    ) -> Result<(), Error> {
+        if self.export {
+            let script = ShellScript::new(self.sleep);
+            let script = self.record(reader, path, &mut std::io::sink(), Some(script))?;
+            script.expect("Export mode is kept").write(writer)
+        } else {
+            self.record(reader, path, writer, None).map(|_| ())
+        }
+    }
+    /// Interpret and run a `CastWright` script, writing the asciicast to a writer, and exporting the shell script if given one. Returns the exported shell script.
+    fn record(
+        &self,
+        reader: &mut impl BufRead,
+        path: Option<PathBuf>,
+        writer: &mut impl Write,
+        export: Option<ShellScript>,
+    ) -> Result<Option<ShellScript>, Error> {
         let (width, height) = get_terminal_size();
         let mut parse_context = ParseContext::new();
         let mut execution_context = ExecutionContext::new();
         let mut cast = AsciiCast::new(writer);
-        let mut line_cnt = 0;
+        cast.format(self.format).map_err(|e| e.with_line(0))?;
         cast.width(width).map_err(|e| e.with_line(0))?;
         cast.height(height).map_err(|e| e.with_line(0))?;
-        execution_context.execute = self.execute;
-        execution_context.preview = self.preview;
+        execution_context.execute = self.execute && export.is_none();
+        execution_context.preview = self.preview && export.is_none();
+        execution_context.export = export;
+        execution_context.record_input = self.record_input;
+        execution_context.injected.clone_from(&self.variables);
         execution_context.width = width;
         execution_context.height = height;
 
@@ -368,39 +608,94 @@ impl CastWright {
             cast.timestamp(timestamp).map_err(|e| e.with_line(0))?;
         }
 
-        for (line_number, line) in reader.lines().enumerate() {
-            Self::run_line(line, &mut parse_context, &mut execution_context, &mut cast)
-                .map_err(|e| e.with_line(line_number + 1))?;
-            line_cnt += 1;
+        let directory = path
+            .as_deref()
+            .and_then(Path::parent)
+            .map_or_else(|| execution_context.directory.clone(), Path::to_path_buf);
+        let mut includes: Vec<PathBuf> = path.into_iter().collect();
+        let line_cnt = Self::run_script(
+            reader,
+            &directory,
+            &mut includes,
+            &mut parse_context,
+            &mut execution_context,
+            &mut cast,
+        )?;
+
+        if execution_context.execute {
+            // A single exit event for the whole recording, like a recorded shell session
+            cast.exit(execution_context.elapsed, execution_context.status)
+                .map_err(|e| e.with_line(line_cnt))?;
         }
-
         cast.finish().map_err(|e| e.with_line(line_cnt))?; // Finish writing the asciicast
 
-        if parse_context.front_matter_state == FrontMatterState::Start {
-            Err(ErrorType::ExpectedClosingDelimiter.with_line(line_cnt + 1))
-        } else if parse_context.expect_continuation {
-            Err(ErrorType::ExpectedContinuation.with_line(line_cnt + 1))
-        } else {
-            Ok(())
+        parse_context
+            .finish()
+            .map_err(|e| e.with_line(line_cnt + 1))?;
+        Ok(execution_context.export)
+    }
+    /// Interpret and run lines of a `CastWright` script, splicing included scripts in place. Returns the number of lines read.
+    ///
+    /// `directory` is where included scripts are resolved from, and `includes` is the stack of canonical paths of scripts being run, used to detect circular includes.
+    fn run_script(
+        reader: &mut impl BufRead,
+        directory: &Path,
+        includes: &mut Vec<PathBuf>,
+        parse_context: &mut ParseContext,
+        execution_context: &mut ExecutionContext,
+        cast: &mut AsciiCast<impl Write>,
+    ) -> Result<usize, Error> {
+        let mut line_cnt = 0;
+        for (line_number, line) in reader.lines().enumerate() {
+            let line_number = line_number + 1;
+            let instruction =
+                Self::parse_line(line, parse_context).map_err(|e| e.with_line(line_number))?;
+            if let Instruction::Include(include) = &instruction {
+                let path = include
+                    .resolve(directory)
+                    .map_err(|e| e.with_line(line_number))?;
+                if includes.contains(&path) {
+                    return Err(ErrorType::CircularInclude.with_line(line_number));
+                }
+                let file =
+                    File::open(&path).map_err(|e| ErrorType::Io(e).with_line(line_number))?;
+                let included_directory = path.parent().unwrap_or(directory).to_path_buf();
+                // Conditional blocks must be balanced within the included script
+                let conditionals = std::mem::take(&mut parse_context.conditionals);
+                includes.push(path);
+                let result = Self::run_script(
+                    &mut BufReader::new(file),
+                    &included_directory,
+                    includes,
+                    parse_context,
+                    execution_context,
+                    cast,
+                )
+                .and_then(|cnt| parse_context.finish().map_err(|e| e.with_line(cnt + 1)));
+                let path = includes.pop().expect("Pushed above");
+                parse_context.conditionals = conditionals;
+                result.map_err(|e| e.in_file(path))?;
+            } else {
+                instruction
+                    .execute(execution_context, cast)
+                    .map_err(|e| e.with_line(line_number))?;
+            }
+            line_cnt = line_number;
         }
+        Ok(line_cnt)
     }
-    /// Interpret and run a line of a `CastWright` script.
-    fn run_line(
+    /// Parse a line of a `CastWright` script.
+    fn parse_line(
         line: Result<String, std::io::Error>,
         parse_context: &mut ParseContext,
-        execution_context: &mut ExecutionContext,
-        cast: &mut AsciiCast<impl std::io::Write>,
-    ) -> Result<(), ErrorType> {
-        let instruction = Instruction::parse(&line?, parse_context)?;
-        instruction.execute(execution_context, cast)?;
-        Ok(())
+    ) -> Result<Instruction, ErrorType> {
+        Instruction::parse(&line?, parse_context)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::BufReader;
 
     #[test]
     fn version_correct() {
@@ -531,6 +826,128 @@ mod tests {
         );
     }
 
+    #[test]
+    fn circular_include() {
+        let error = CastWright::new()
+            .run_file("tests/include/circular-a.cwrt", &mut std::io::sink())
+            .unwrap_err();
+        let file = PathBuf::from("tests/include/circular-b.cwrt")
+            .canonicalize()
+            .unwrap();
+        assert_eq!(error, ErrorType::CircularInclude.with_line(3).in_file(file));
+    }
+
+    #[test]
+    fn error_in_included_script() {
+        let error = CastWright::new()
+            .run_file("tests/include/include-broken.cwrt", &mut std::io::sink())
+            .unwrap_err();
+        let file = PathBuf::from("tests/include/broken.cwrt")
+            .canonicalize()
+            .unwrap();
+        assert_eq!(
+            error,
+            ErrorType::ExpectedContinuation.with_line(3).in_file(file)
+        );
+    }
+
+    #[test]
+    fn include_not_found() {
+        let text = r"
+            % Hello
+            @include tests/include/not-found.cwrt
+        ";
+        let text = text.trim();
+        let mut reader = BufReader::new(text.as_bytes());
+        let error = CastWright::new()
+            .run(&mut reader, &mut std::io::sink())
+            .unwrap_err();
+        assert!(matches!(error.error, ErrorType::Io(_)));
+        assert_eq!(error.line, 2);
+        assert_eq!(error.file, None);
+    }
+
+    #[test]
+    fn export_shell_script() {
+        let text = r#"
+            @@set NAME world
+            ! Greeting
+            % Hello, ${NAME}!
+            ~ 500ms
+            @hidden
+            $ mkdir -p demo
+            @if env CASTWRIGHT_UNSET_VARIABLE
+            $ echo skipped
+            @else
+            $ echo kept
+            @end
+            $ echo "Multi-" \
+            > "line"
+            @expect failure
+            $ ~{lss}ls missing
+        "#;
+        let text = text.trim();
+        let mut reader = BufReader::new(text.as_bytes());
+        let mut writer = Vec::new();
+        CastWright::new()
+            .execute(true)
+            .variable("NAME", "there")
+            .export(true)
+            .run(&mut reader, &mut writer)
+            .unwrap();
+        let expected = r#"#!/bin/sh
+set -e
+
+# Greeting
+printf '%s\n' 'Hello, there!'
+# Wait 0.5s
+mkdir -p demo
+echo kept
+echo "Multi-" "line"
+{
+ls missing
+} || true
+"#;
+        assert_eq!(String::from_utf8(writer).unwrap(), expected);
+    }
+
+    #[test]
+    fn exit_event() {
+        let text = r"
+            @@interval 0
+            @@expect any
+            $ exit 2
+            $ exit 3
+            % Done
+        ";
+        let text = text.trim();
+        let mut writer = Vec::new();
+        CastWright::new()
+            .execute(true)
+            .format(Format::V3)
+            .run(&mut BufReader::new(text.as_bytes()), &mut writer)
+            .unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        let exits = output
+            .lines()
+            .filter(|line| line.contains(r#","x","#))
+            .count();
+        assert_eq!(exits, 1, "Output: {output}");
+        assert!(
+            output.trim_end().ends_with(r#","x","3"]"#),
+            "Output: {output}"
+        );
+
+        // Without execution, there is no exit status to record
+        let mut writer = Vec::new();
+        CastWright::new()
+            .format(Format::V3)
+            .run(&mut BufReader::new(text.as_bytes()), &mut writer)
+            .unwrap();
+        let output = String::from_utf8(writer).unwrap();
+        assert!(!output.contains(r#","x","#), "Output: {output}");
+    }
+
     #[test]
     fn execution_context_consume_temporary() {
         let mut context = ExecutionContext::new();
@@ -543,9 +960,20 @@ mod tests {
             line_continuation: " \\".to_string(),
             hidden: false,
             expect: None,
-            interval: 100_000,
+            interval: None,
             start_lag: 0,
             end_lag: 0,
+            jitter: 0,
+            pause: 0,
+            typos: 0,
+            seed: None,
+            wpm: 0,
+            paste: None,
+            paste_pause: 0,
+            highlight: false,
+            status_indicator: String::new(),
+            line_pause: 0,
+            print_pace: Pace::Character,
         };
         let calculated_config = context
             .persistent
@@ -1,7 +1,10 @@
 #![warn(clippy::all, clippy::nursery, clippy::pedantic, clippy::cargo)]
 
 use argh::FromArgs;
-use castwright::{CastWright, Error, ErrorType, VERSION};
+use castwright::{
+    AsciiCast, CastWright, DEFAULT_PROMPT, Error, ErrorType, Format, Header, Reader, TimedEvent,
+    VERSION, cap_idle, concat, cut, import_cast, import_tape, is_valid_variable_name, speed,
+};
 use disperror::DispError;
 use std::{
     fs::File,
@@ -12,6 +15,7 @@ use std::{
 /// 🎥 Scripted terminal recording.
 #[derive(FromArgs)]
 #[argh(help_triggers("-h", "--help"))]
+#[allow(clippy::struct_excessive_bools)]
 struct Args {
     // Input and output
     /// the path to the input file (`CastWright` script `.cwrt`), or stdin if not provided
@@ -28,22 +32,304 @@ struct Args {
     /// include timestamp information in the output
     #[argh(switch, short = 't')]
     timestamp: bool,
+    /// record simulated keystrokes of commands as input events
+    #[argh(switch)]
+    record_input: bool,
+    /// version of the asciicast format to write, `v2` (default) or `v3`
+    #[argh(option, default = "Format::V2", from_str_fn(format))]
+    format: Format,
+    /// write a POSIX shell script of the commands instead of an asciicast, without executing them
+    #[argh(switch)]
+    export: bool,
+    /// with `--export`, turn waits into `sleep` commands instead of comments
+    #[argh(switch)]
+    sleep: bool,
+    /// define a variable in the form of `NAME=value`, overriding `@@set` in the script; Can be repeated
+    #[argh(option, from_str_fn(variable))]
+    var: Vec<(String, String)>,
 
     // Help
     /// show version information and exit
     #[argh(switch, short = 'v')]
     version: bool,
+
+    // Post-processing
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+/// Subcommands for post-processing existing asciicasts.
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Speed(Speed),
+    Cut(Cut),
+    Idle(Idle),
+    Concat(Concat),
+    Import(Import),
+}
+
+/// Scale the speed of an asciicast.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "speed")]
+struct Speed {
+    /// speed factor, e.g. `1.5` to play 1.5 times as fast
+    #[argh(positional, from_str_fn(positive))]
+    factor: f64,
+    /// the path to the input asciicast, or stdin if not provided
+    #[argh(option, short = 'i')]
+    input: Option<String>,
+    /// the path to the output asciicast, or stdout if not provided
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+/// Remove the events between two markers, or after a marker if only one is given.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "cut")]
+struct Cut {
+    /// label of the marker to cut from
+    #[argh(positional)]
+    from: String,
+    /// label of the marker to cut to, or the end of the asciicast if not provided
+    #[argh(positional)]
+    to: Option<String>,
+    /// the path to the input asciicast, or stdin if not provided
+    #[argh(option, short = 'i')]
+    input: Option<String>,
+    /// the path to the output asciicast, or stdout if not provided
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+/// Cap the idle time between events of an asciicast.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "idle")]
+struct Idle {
+    /// maximum idle time in seconds, e.g. `1.5`
+    #[argh(positional, from_str_fn(positive))]
+    limit: f64,
+    /// the path to the input asciicast, or stdin if not provided
+    #[argh(option, short = 'i')]
+    input: Option<String>,
+    /// the path to the output asciicast, or stdout if not provided
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+/// Concatenate asciicasts sequentially, with a marker at the start of each.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "concat")]
+struct Concat {
+    /// the paths to the input asciicasts, in order
+    #[argh(positional)]
+    inputs: Vec<String>,
+    /// gap between asciicasts in seconds, defaulting to `0`
+    #[argh(option, default = "0.0", from_str_fn(non_negative))]
+    gap: f64,
+    /// the path to the output asciicast, or stdout if not provided
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+/// Convert an asciicast or a VHS tape into a `CastWright` script.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "import")]
+struct Import {
+    /// read the input as a VHS tape, implied if the input path ends with `.tape`
+    #[argh(switch)]
+    tape: bool,
+    /// regex matching the shell prompt before commands in the output, defaulting to a line prefix without whitespace ending with `$ `, `# `, `% `, `> ` or `❯ `; Unused if the asciicast has input events
+    #[argh(option, default = "DEFAULT_PROMPT.to_string()")]
+    prompt: String,
+    /// the path to the input asciicast or tape, or stdin if not provided
+    #[argh(option, short = 'i')]
+    input: Option<String>,
+    /// the path to the output script (`.cwrt`), or stdout if not provided
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+}
+
+impl Command {
+    /// Run the subcommand.
+    fn run(self) -> Result<(), Error> {
+        match self {
+            Self::Speed(Speed {
+                factor,
+                input,
+                output,
+            }) => process(input.as_deref(), output.as_deref(), |header, events| {
+                speed(header, events, factor)
+            }),
+            Self::Cut(Cut {
+                from,
+                to,
+                input,
+                output,
+            }) => process(input.as_deref(), output.as_deref(), |_, events| {
+                Ok(cut(events, from, to))
+            }),
+            Self::Idle(Idle {
+                limit,
+                input,
+                output,
+            }) => process(input.as_deref(), output.as_deref(), |_, events| {
+                Ok(cap_idle(events, micros(limit)))
+            }),
+            Self::Concat(Concat {
+                inputs,
+                gap,
+                output,
+            }) => {
+                if inputs.is_empty() {
+                    return Err(ErrorType::Io(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "no input asciicast given",
+                    ))
+                    .with_line(0));
+                }
+                let readers = inputs
+                    .iter()
+                    .map(|path| open(path))
+                    .collect::<Result<_, _>>()?;
+                let (header, events) = concat(readers, micros(gap));
+                write(output.as_deref(), header, events)
+            }
+            Self::Import(Import {
+                tape,
+                prompt,
+                input,
+                output,
+            }) => {
+                let writer: &mut BufWriter<dyn Write> = match output.as_deref() {
+                    Some(path) => &mut BufWriter::new(create(path)?),
+                    None => &mut BufWriter::new(std::io::stdout().lock()),
+                };
+                let tape = tape
+                    || input.as_deref().is_some_and(|path| {
+                        Path::new(path)
+                            .extension()
+                            .is_some_and(|extension| extension.eq_ignore_ascii_case("tape"))
+                    });
+                if !tape {
+                    return import_cast(read(input.as_deref())?, &prompt, writer);
+                }
+                let unsupported = match input.as_deref() {
+                    Some(path) => {
+                        let file = File::open(path).map_err(|e| ErrorType::Io(e).with_line(0))?;
+                        import_tape(BufReader::new(file), writer).map_err(|mut e| {
+                            e.file = Some(path.into());
+                            e
+                        })?
+                    }
+                    None => import_tape(std::io::stdin().lock(), writer)?,
+                };
+                for error in unsupported {
+                    eprintln!("Warning: {error}, left as a comment");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Read an asciicast, edit its header and events, and write the result, streaming from input to output.
+fn process<I>(
+    input: Option<&str>,
+    output: Option<&str>,
+    edit: impl FnOnce(&mut Header, Reader<Box<dyn BufRead>>) -> Result<I, Error>,
+) -> Result<(), Error>
+where
+    I: Iterator<Item = Result<TimedEvent, Error>>,
+{
+    let reader = read(input)?;
+    let mut header = reader.header().clone();
+    let events = edit(&mut header, reader)?;
+    write(output, header, events)
+}
+
+/// Read an asciicast from the input file, or stdin if not provided.
+fn read(input: Option<&str>) -> Result<Reader<Box<dyn BufRead>>, Error> {
+    input.map_or_else(
+        || Reader::new(Box::new(std::io::stdin().lock()) as Box<dyn BufRead>),
+        open,
+    )
+}
+
+/// Open an asciicast at the given path, attributing errors in its header and events to the file.
+fn open(path: &str) -> Result<Reader<Box<dyn BufRead>>, Error> {
+    let file = File::open(path).map_err(|e| ErrorType::Io(e).with_line(0))?;
+    let reader =
+        Reader::new(Box::new(BufReader::new(file)) as Box<dyn BufRead>).map_err(|mut e| {
+            e.file = Some(path.into());
+            e
+        })?;
+    Ok(reader.file(path))
 }
 
-/// Create or open a file at the given path.
-fn file(path: &str, create: bool) -> Result<File, Error> {
-    let path = Path::new(path);
-    let file = if create {
-        File::create(path)
-    } else {
-        File::open(path)
+/// Write an asciicast with given header and events to the output file, or stdout if not provided.
+fn write(
+    output: Option<&str>,
+    header: Header,
+    events: impl Iterator<Item = Result<TimedEvent, Error>>,
+) -> Result<(), Error> {
+    let writer: &mut BufWriter<dyn Write> = match output {
+        Some(path) => &mut BufWriter::new(create(path)?),
+        None => &mut BufWriter::new(std::io::stdout().lock()),
     };
-    file.map_err(|e| ErrorType::Io(e).with_line(0))
+    let mut cast = AsciiCast::new(writer);
+    cast.header(header).map_err(|e| e.with_line(0))?;
+    for event in events {
+        cast.write_event(&event?).map_err(|e| e.with_line(0))?;
+    }
+    cast.finish().map_err(|e| e.with_line(0))
+}
+
+/// Create a file at the given path.
+fn create(path: &str) -> Result<File, Error> {
+    File::create(Path::new(path)).map_err(|e| ErrorType::Io(e).with_line(0))
+}
+
+/// Parse a variable definition in the form of `NAME=value`.
+fn variable(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `NAME=value`, got `{s}`"))?;
+    if !is_valid_variable_name(name) {
+        return Err(format!("invalid variable name `{name}`"));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Parse a positive number.
+fn positive(s: &str) -> Result<f64, String> {
+    match s.parse() {
+        Ok(value) if value > 0.0 && f64::is_finite(value) => Ok(value),
+        _ => Err(format!("expected a positive number, got `{s}`")),
+    }
+}
+
+/// Parse a non-negative number.
+fn non_negative(s: &str) -> Result<f64, String> {
+    match s.parse() {
+        Ok(value) if value >= 0.0 && f64::is_finite(value) => Ok(value),
+        _ => Err(format!("expected a non-negative number, got `{s}`")),
+    }
+}
+
+/// Convert non-negative seconds to microseconds.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn micros(seconds: f64) -> u128 {
+    (seconds * 1_000_000.0).round() as u128
+}
+
+/// Parse a version of the asciicast format.
+fn format(s: &str) -> Result<Format, String> {
+    match s {
+        "v2" | "2" => Ok(Format::V2),
+        "v3" | "3" => Ok(Format::V3),
+        _ => Err(format!("expected `v2` or `v3`, got `{s}`")),
+    }
 }
 
 /// Display a link in the terminal.
@@ -72,22 +358,34 @@ fn main() -> Result<(), DispError<Error>> {
         version();
         return Ok(());
     }
-
-    let mut reader: &mut dyn BufRead = match &args.input {
-        Some(path) => &mut BufReader::new(file(path, false)?),
-        None => &mut std::io::stdin().lock(),
-    };
+    if let Some(command) = args.command {
+        command.run()?;
+        return Ok(());
+    }
 
     let mut writer: &mut BufWriter<dyn Write> = match &args.output {
-        Some(path) => &mut BufWriter::new(file(path, true)?),
+        Some(path) => &mut BufWriter::new(create(path)?),
         None => &mut BufWriter::new(std::io::stdout().lock()),
     };
 
-    CastWright::new()
+    let castwright = CastWright::new()
         .execute(args.execute)
         .timestamp(args.timestamp)
         .preview(args.output.is_some())
-        .run(&mut reader, &mut writer)?;
+        .record_input(args.record_input)
+        .format(args.format)
+        .export(args.export)
+        .sleep(args.sleep);
+    let castwright = args
+        .var
+        .into_iter()
+        .fold(castwright, |castwright, (name, value)| {
+            castwright.variable(name, value)
+        });
+    match &args.input {
+        Some(path) => castwright.run_file(path, &mut writer)?,
+        None => castwright.run(&mut std::io::stdin().lock(), &mut writer)?,
+    }
 
     Ok(())
 }
@@ -4,11 +4,15 @@ mod cd;
 
 use super::{ErrorType, ExecutionContext};
 use cd::Cd;
+use pty_process::{
+    Size,
+    blocking::{Command, Pty, open},
+};
 use std::{
     io::Read,
-    process::Child,
+    os::unix::process::ExitStatusExt,
+    process::{Child, ExitStatus},
 };
-use pty_process::{blocking::{open, Command, Pty}, Size};
 
 /// Execute a command using given shell, returning its output as an iterator, with `\n` replaced by `\r\n`.
 pub fn execute_command(
@@ -44,6 +48,8 @@ pub struct ReaderIterator {
     reader: Option<Pty>,
     /// Buffer for reading output.
     buffer: [u8; 1024],
+    /// Exit code of the child process, once it has exited.
+    code: i32,
 }
 
 impl ReaderIterator {
@@ -53,6 +59,7 @@ impl ReaderIterator {
             child: None,
             reader: None,
             buffer: [0; 1024],
+            code: 0,
         }
     }
     /// Create a new [`ReaderIterator`] from a [`Child`] and reference to [`Pty`].
@@ -61,8 +68,21 @@ impl ReaderIterator {
             child: Some(child),
             reader: Some(reader),
             buffer: [0; 1024],
+            code: 0,
         }
     }
+    /// Exit code of the command, or `0` if it has not exited yet. A command terminated by a signal is considered to exit with `128 + signal`, like in bash.
+    pub const fn code(&self) -> i32 {
+        self.code
+    }
+}
+
+/// Get the exit code from given exit status.
+fn exit_code(status: ExitStatus) -> i32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
 }
 
 impl Iterator for ReaderIterator {
@@ -88,6 +108,7 @@ impl Iterator for ReaderIterator {
                 // Discard the child and reader
                 let _ = self.child.take().unwrap().wait();
                 self.reader.take();
+                self.code = exit_code(status);
 
                 let item = if status.success() {
                     Ok(None)
@@ -109,7 +130,7 @@ impl Iterator for ReaderIterator {
 
                 return Some(Err(ErrorType::Io(e)));
             }
-        };
+        }
         // Still running, try to read from the reader
         let item = match reader.read(&mut self.buffer) {
             // No output available yet
@@ -133,6 +154,7 @@ impl Iterator for ReaderIterator {
                         Ok(status) => status,
                         Err(e) => return Some(Err(ErrorType::Io(e))),
                     };
+                    self.code = exit_code(status);
                     if status.success() {
                         Ok(None)
                     } else {
@@ -267,7 +289,19 @@ mod tests {
         assert_eq!(actual, expected);
 
         let duration = second.unwrap().duration_since(first.unwrap());
-        assert!(duration >= Duration::from_secs(1), "Duration: {duration:?}",);
+        assert!(duration >= Duration::from_secs(1), "Duration: {duration:?}");
+    }
+
+    #[test]
+    fn exit_status() {
+        let mut context = ExecutionContext::new();
+        let mut reader = execute_command(&mut context, "exit 3").unwrap();
+        for chunk in &mut reader {
+            if chunk.is_err() {
+                break;
+            }
+        }
+        assert_eq!(reader.code(), 3);
     }
 
     #[test]
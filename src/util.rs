@@ -39,6 +39,113 @@ pub fn parse_loose_string(s: &str) -> Result<String, ErrorType> {
         Ok(s.to_string())
     }
 }
+/// Check if a string is a valid variable name, i.e. an ASCII letter or `_`, followed by ASCII letters, digits or `_`.
+#[must_use]
+pub fn is_valid_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+/// Interpolate `${NAME}` occurrences in a string, using `lookup` to resolve variable names. `$${` is unescaped to a literal `${`. Undefined variables and invalid names are left as they are.
+pub fn interpolate<'a>(s: &str, lookup: impl Fn(&str) -> Option<&'a str>) -> String {
+    let lookup = |name: &str| {
+        if is_valid_variable_name(name) {
+            lookup(name)
+        } else {
+            None
+        }
+    };
+    substitute(s, lookup, true)
+}
+/// Substitute positional arguments `${1}`, `${2}`, ... in a string. Other references and escaped `$${` are left as they are, so they can be interpolated later.
+pub fn substitute_arguments(s: &str, arguments: &[String]) -> String {
+    let lookup = |name: &str| {
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let index: usize = name.parse().ok()?;
+        arguments.get(index.checked_sub(1)?).map(String::as_str)
+    };
+    substitute(s, lookup, false)
+}
+/// Substitute `${NAME}` occurrences in a string, using `lookup` to resolve names. If `unescape` is `true`, `$${` is unescaped to a literal `${`, otherwise it is left as it is.
+fn substitute<'a>(s: &str, lookup: impl Fn(&str) -> Option<&'a str>, unescape: bool) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find("${") {
+        if rest[..pos].ends_with('$') {
+            // Escaped `$${`
+            if unescape {
+                result.push_str(&rest[..pos - 1]);
+            } else {
+                result.push_str(&rest[..pos]);
+            }
+            result.push_str("${");
+            rest = &rest[pos + 2..];
+            continue;
+        }
+        result.push_str(&rest[..pos]);
+        let after = &rest[pos + 2..];
+        let Some(end) = after.find('}') else {
+            // No closing brace, output the rest as it is
+            result.push_str(&rest[pos..]);
+            rest = "";
+            break;
+        };
+        match lookup(&after[..end]) {
+            Some(value) => result.push_str(value),
+            None => result.push_str(&rest[pos..=pos + 2 + end]),
+        }
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+/// Parse whitespace-separated arguments, each of which is either a [`String`](parse_loose_string) enclosed in `"`, or a raw word.
+pub fn parse_arguments(s: &str) -> Result<Vec<String>, ErrorType> {
+    let mut arguments = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let end = if rest.starts_with('"') {
+            closing_quote(rest).map_or(rest.len(), |i| i + 1)
+        } else {
+            rest.find(char::is_whitespace).unwrap_or(rest.len())
+        };
+        let (argument, remaining) = rest.split_at(end);
+        if argument.starts_with('"') {
+            arguments.push(serde_json::from_str(argument)?);
+        } else {
+            arguments.push(argument.to_string());
+        }
+        rest = remaining.trim_start();
+    }
+    Ok(arguments)
+}
+/// Find the byte index of the `"` closing the string that `s` starts with, skipping escaped characters.
+fn closing_quote(s: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+/// Parse a percentage, like `30%`, into an integer between 0 and 100.
+pub fn parse_percentage(s: &str) -> Result<u8, ErrorType> {
+    let num = s.strip_suffix('%').ok_or(ErrorType::MalformedInstruction)?;
+    let num: u8 = num.parse()?;
+    if num > 100 {
+        Err(ErrorType::MalformedInstruction)
+    } else {
+        Ok(num)
+    }
+}
 /// Detect terminal size, defaulting to 80x24 if it fails.
 pub fn get_terminal_size() -> (u16, u16) {
     terminal_size().map_or((80, 24), |(Width(w), Height(h))| (w, h))
@@ -62,6 +169,35 @@ pub fn timestamp() -> Result<u64, ErrorType> {
     Ok(timestamp)
 }
 
+/// A seeded pseudo-random number generator ([SplitMix64](https://prng.di.unimi.it/splitmix64.c)), so that randomness is reproducible across runs.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Create a new generator from given seed.
+    pub const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+    /// Generate the next pseudo-random `u64`.
+    pub const fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Return `true` with given probability, in percent.
+    pub const fn chance(&mut self, percent: u8) -> bool {
+        self.next_u64() % 100 < percent as u64
+    }
+    /// Generate a pseudo-random integer in the inclusive range `[-bound, bound]`.
+    pub fn next_offset(&mut self, bound: u8) -> i128 {
+        let bound = i128::from(bound);
+        let range = u64::try_from(2 * bound + 1).expect("Bound is small");
+        i128::from(self.next_u64() % range) - bound
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,6 +235,123 @@ mod tests {
         }
     }
 
+    #[test]
+    fn variable_name() {
+        let valid = ["NAME", "_name", "name_1", "_"];
+        for name in &valid {
+            assert!(
+                is_valid_variable_name(name),
+                "Expected `{name}` to be valid"
+            );
+        }
+        let invalid = ["", "1name", "na-me", "na me", "名字"];
+        for name in &invalid {
+            assert!(
+                !is_valid_variable_name(name),
+                "Expected `{name}` to be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn interpolation() {
+        let variables = HashMap::from([
+            ("NAME".to_string(), "castwright".to_string()),
+            ("VERSION".to_string(), "0.1.0".to_string()),
+            ("A B".to_string(), "invalid".to_string()),
+        ]);
+        let lookup = |name: &str| variables.get(name).map(String::as_str);
+        let cases = [
+            ("${NAME} v${VERSION}", "castwright v0.1.0"),
+            ("no variables", "no variables"),
+            ("$${NAME}", "${NAME}"),
+            ("$$${NAME}", "$${NAME}"),
+            ("${UNDEFINED}", "${UNDEFINED}"),
+            ("${NAME", "${NAME"),
+            ("$NAME ${}", "$NAME ${}"),
+            ("${NAME}${NAME}", "castwrightcastwright"),
+            ("${A B}", "${A B}"),
+        ];
+        for (input, expected) in &cases {
+            assert_eq!(interpolate(input, lookup), *expected, "Input: `{input}`");
+        }
+    }
+
+    #[test]
+    fn arguments_substitution() {
+        let arguments = ["src/main.rs".to_string(), "cargo test".to_string()];
+        let cases = [
+            ("cat ${1} && ${2}", "cat src/main.rs && cargo test"),
+            ("${3} ${0} ${01}", "${3} ${0} src/main.rs"),
+            ("${NAME} $${1} ${+1}", "${NAME} $${1} ${+1}"),
+        ];
+        for (input, expected) in &cases {
+            assert_eq!(
+                substitute_arguments(input, &arguments),
+                *expected,
+                "Input: `{input}`"
+            );
+        }
+    }
+
+    #[test]
+    fn arguments() {
+        let cases: [(&str, &[&str]); 5] = [
+            ("", &[]),
+            ("  one two  ", &["one", "two"]),
+            ("\"with space\" raw", &["with space", "raw"]),
+            ("\"escaped \\\" quote\"", &["escaped \" quote"]),
+            ("a\"b c", &["a\"b", "c"]),
+        ];
+        for (input, expected) in &cases {
+            assert_eq!(
+                parse_arguments(input).unwrap(),
+                *expected,
+                "Input: `{input}`"
+            );
+        }
+        let err = parse_arguments("\"unclosed").unwrap_err();
+        assert!(
+            matches!(err, ErrorType::Json(_)),
+            "Expected Json error, got {err:?}"
+        );
+    }
+
+    #[test]
+    fn percentage() {
+        let percentages = [("0%", 0), ("30%", 30), ("100%", 100)];
+        for (input, expected) in &percentages {
+            assert_eq!(parse_percentage(input).unwrap(), *expected);
+        }
+        let bad_percentages = ["30", "101%", "-1%", "%", "1.5%"];
+        for input in &bad_percentages {
+            let err = parse_percentage(input).unwrap_err();
+            assert!(
+                matches!(err, ErrorType::MalformedInstruction),
+                "Expected MalformedInstruction, got {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rng_reproducible() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+        let a = (0..8).map(|_| a.next_u64()).collect::<Vec<_>>();
+        let b = (0..8).map(|_| b.next_u64()).collect::<Vec<_>>();
+        let c = (0..8).map(|_| c.next_u64()).collect::<Vec<_>>();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let offset = rng.next_offset(30);
+            assert!((-30..=30).contains(&offset), "Offset: {offset}");
+        }
+        assert_eq!(rng.next_offset(0), 0);
+    }
+
     #[test]
     fn loose_string_error() {
         let strings = ["\"hello\" world\"", "\"hello\" world\" again\""];
@@ -1,8 +1,6 @@
-use castwright::{CastWright, Error, ErrorType};
-use std::{
-    fs::File,
-    io::{BufReader, Read},
-};
+use castwright::{CastWright, Error, EventKind, Reader, TimedEvent};
+use std::{fs::File, io::BufReader};
+use unicode_segmentation::UnicodeSegmentation;
 
 const INPUT_DIR: &str = "tests/input/";
 const OUTPUT_DIR: &str = "tests/output/";
@@ -11,10 +9,10 @@ const FAILURE_DIR: &str = "tests/failure/";
 
 // Input-Output tests, without execution.
 
-/// A test case, contains the name of the test, the input file and the expected output file.
+/// A test case, contains the name of the test, the path to the input file and the expected output file.
 struct TestCase {
     name: String,
-    input: File,
+    input: String,
     output: File,
 }
 
@@ -25,7 +23,7 @@ fn test_cases() -> impl Iterator<Item = TestCase> {
         let entry = entry.unwrap();
         let name = entry.file_name().into_string().unwrap();
         let name = name.trim_end_matches(".cwrt").to_string();
-        let input = File::open(format!("{INPUT_DIR}{name}.cwrt")).unwrap();
+        let input = format!("{INPUT_DIR}{name}.cwrt");
         let output = File::open(format!("{OUTPUT_DIR}{name}.cast")).unwrap();
         TestCase {
             name,
@@ -38,30 +36,67 @@ fn test_cases() -> impl Iterator<Item = TestCase> {
 #[test]
 fn input_output_tests() -> Result<(), Error> {
     let castwright = CastWright::new();
-    for mut case in test_cases() {
-        let mut reader = BufReader::new(case.input);
+    for case in test_cases() {
         let mut writer = Vec::new();
-        castwright.run(&mut reader, &mut writer)?;
+        castwright.run_file(&case.input, &mut writer)?;
 
-        let mut expected = Vec::new();
-        case.output
-            .read_to_end(&mut expected)
-            .map_err(|err| ErrorType::Io(err).with_line(0))?;
+        // Compare the asciicasts semantically (to avoid differences in line endings or key order)
+        let output = Reader::new(writer.as_slice())?;
+        let expected = Reader::new(BufReader::new(case.output))?;
+        assert_eq!(
+            output.header(),
+            expected.header(),
+            "Test case: {}",
+            case.name
+        );
 
-        let output = String::from_utf8(writer).unwrap();
-        let expected = String::from_utf8(expected).unwrap();
-
-        // Compare output line by line (to avoid differences in line endings)
-        for (i, (output_line, expected_line)) in output.lines().zip(expected.lines()).enumerate() {
+        let output = output.collect::<Result<Vec<_>, _>>()?;
+        let expected = expected.collect::<Result<Vec<_>, _>>()?;
+        for (i, (output_event, expected_event)) in output.iter().zip(&expected).enumerate() {
             assert_eq!(
-                output_line,
-                expected_line,
-                "Test case: {}, line: {}",
+                output_event,
+                expected_event,
+                "Test case: {}, event: {}",
                 case.name,
                 i + 1
             );
         }
+        assert_eq!(output.len(), expected.len(), "Test case: {}", case.name);
+    }
+    Ok(())
+}
+
+#[test]
+fn non_ascii_graphemes() -> Result<(), Error> {
+    let mut writer = Vec::new();
+    CastWright::new().run_file(format!("{INPUT_DIR}non-ascii.cwrt"), &mut writer)?;
+
+    // Collect the data of output events
+    let events: Vec<String> = Reader::new(writer.as_slice())?
+        .filter_map(|event| match event {
+            Ok(TimedEvent {
+                kind: EventKind::Output(data),
+                ..
+            }) => Some(Ok(data)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+        .collect::<Result<_, _>>()?;
+
+    // Each typed event should be a whole grapheme cluster, i.e. no cluster spans two events
+    for pair in events.windows(2) {
+        let joined = format!("{}{}", pair[0], pair[1]);
+        assert_eq!(
+            joined.graphemes(true).count(),
+            pair[0].graphemes(true).count() + pair[1].graphemes(true).count(),
+            "Grapheme split between {:?} and {:?}",
+            pair[0],
+            pair[1]
+        );
     }
+    // Combining characters are typed along with their base character
+    assert!(events.iter().any(|event| event == "اً"));
+    assert!(events.iter().any(|event| event == "दु"));
     Ok(())
 }
 